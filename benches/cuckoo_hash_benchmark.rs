@@ -1,23 +1,258 @@
-use criterion::{black_box, Criterion, criterion_group, criterion_main};
+use std::collections::HashSet;
+
+use criterion::{black_box, BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
 use rand::Rng;
 use cuckoo_rs::cuckoo::CuckooHashTable;
 
+const ELEMENTS: usize = 100_000;
+
+/// Fractions of the table's slots that are occupied before measuring.
+/// A table with capacity `c` has `2 * c` slots across both bucket groups.
+const LOAD_FACTORS: [f64; 3] = [0.1, 0.25, 0.4];
+
 fn generate_random_numbers(count: usize) -> Vec<i32> {
     let mut rng = rand::thread_rng();
     (0..count).map(|_| rng.gen()).collect()
 }
 
+/// Returns `ELEMENTS` keys that are all distinct, split into the keys
+/// that get inserted and an equally sized batch of keys that are not.
+fn generate_hits_and_misses() -> (Vec<i32>, Vec<i32>) {
+    let mut seen = HashSet::new();
+    let mut keys = Vec::with_capacity(2 * ELEMENTS);
+    for x in generate_random_numbers(4 * ELEMENTS) {
+        if keys.len() == 2 * ELEMENTS {
+            break;
+        }
+        if seen.insert(x) {
+            keys.push(x);
+        }
+    }
+    let misses = keys.split_off(ELEMENTS);
+    (keys, misses)
+}
+
+/// Builds a table sized so that `keys` fill it to `load`.
+fn prefilled_table(keys: &[i32], load: f64) -> CuckooHashTable<i32> {
+    let capacity = (keys.len() as f64 / (2.0 * load)) as usize;
+    let mut table = CuckooHashTable::with_capacity(capacity);
+    for &x in keys {
+        table.insert(x);
+    }
+    table
+}
+
 fn criterion_insert(c: &mut Criterion) {
-    let numbers_1m = generate_random_numbers(1_000_000);
-    c.bench_function("insert 1M elements", |b| {
-        let mut table = CuckooHashTable::new();
-        b.iter(|| {
-            for &num in black_box(&numbers_1m) {
-                table.insert(num);
+    let numbers = generate_random_numbers(ELEMENTS);
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(ELEMENTS as u64));
+    group.bench_function("cuckoo", |b| {
+        b.iter_batched(
+            CuckooHashTable::new,
+            |mut table| {
+                for &num in black_box(&numbers) {
+                    table.insert(num);
+                }
+                table
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("std HashSet", |b| {
+        b.iter_batched(
+            HashSet::new,
+            |mut set| {
+                for &num in black_box(&numbers) {
+                    set.insert(num);
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("hashbrown HashSet", |b| {
+        b.iter_batched(
+            hashbrown::HashSet::new,
+            |mut set| {
+                for &num in black_box(&numbers) {
+                    set.insert(num);
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn criterion_contains(c: &mut Criterion) {
+    let (hits, misses) = generate_hits_and_misses();
+    let std_set: HashSet<i32> = hits.iter().copied().collect();
+    let hashbrown_set: hashbrown::HashSet<i32> = hits.iter().copied().collect();
+    for (name, keys) in [("contains hit", &hits), ("contains miss", &misses)] {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Elements(ELEMENTS as u64));
+        for load in LOAD_FACTORS {
+            let table = prefilled_table(&hits, load);
+            group.bench_with_input(BenchmarkId::new("cuckoo", load), keys, |b, keys| {
+                b.iter(|| keys.iter().filter(|&x| table.contains(x)).count())
+            });
+        }
+        group.bench_with_input("std HashSet", keys, |b, keys| {
+            b.iter(|| keys.iter().filter(|&x| std_set.contains(x)).count())
+        });
+        group.bench_with_input("hashbrown HashSet", keys, |b, keys| {
+            b.iter(|| keys.iter().filter(|&x| hashbrown_set.contains(x)).count())
+        });
+        group.finish();
+    }
+}
+
+fn criterion_remove(c: &mut Criterion) {
+    let (hits, _) = generate_hits_and_misses();
+    let std_set: HashSet<i32> = hits.iter().copied().collect();
+    let hashbrown_set: hashbrown::HashSet<i32> = hits.iter().copied().collect();
+    let mut group = c.benchmark_group("remove");
+    group.throughput(Throughput::Elements(ELEMENTS as u64));
+    for load in LOAD_FACTORS {
+        let table = prefilled_table(&hits, load);
+        group.bench_function(BenchmarkId::new("cuckoo", load), |b| {
+            b.iter_batched(
+                || table.clone(),
+                |mut table| {
+                    for x in black_box(&hits) {
+                        table.remove(x);
+                    }
+                    table
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("cuckoo remove_many", load), |b| {
+            b.iter_batched(
+                || table.clone(),
+                |mut table| {
+                    table.remove_many(black_box(&hits));
+                    table
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.bench_function("std HashSet", |b| {
+        b.iter_batched(
+            || std_set.clone(),
+            |mut set| {
+                for x in black_box(&hits) {
+                    set.remove(x);
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("hashbrown HashSet", |b| {
+        b.iter_batched(
+            || hashbrown_set.clone(),
+            |mut set| {
+                for x in black_box(&hits) {
+                    set.remove(x);
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Insert(i32),
+    Remove(i32),
+    Contains(i32),
+}
+
+/// A read-heavy workload: 80% lookups, 10% inserts and 10% removals over a
+/// key space twice the size of the prefilled set.
+fn generate_mixed_workload(keys: &[i32], misses: &[i32]) -> Vec<Op> {
+    let mut rng = rand::thread_rng();
+    (0..ELEMENTS)
+        .map(|_| {
+            let key = if rng.gen() {
+                keys[rng.gen_range(0..keys.len())]
+            } else {
+                misses[rng.gen_range(0..misses.len())]
+            };
+            match rng.gen_range(0..10) {
+                0 => Op::Insert(key),
+                1 => Op::Remove(key),
+                _ => Op::Contains(key),
             }
         })
+        .collect()
+}
+
+fn criterion_mixed(c: &mut Criterion) {
+    let (hits, misses) = generate_hits_and_misses();
+    let ops = generate_mixed_workload(&hits, &misses);
+    let std_set: HashSet<i32> = hits.iter().copied().collect();
+    let hashbrown_set: hashbrown::HashSet<i32> = hits.iter().copied().collect();
+    let mut group = c.benchmark_group("mixed");
+    group.throughput(Throughput::Elements(ELEMENTS as u64));
+    for load in LOAD_FACTORS {
+        let table = prefilled_table(&hits, load);
+        group.bench_function(BenchmarkId::new("cuckoo", load), |b| {
+            b.iter_batched(
+                || table.clone(),
+                |mut table| {
+                    for &op in black_box(&ops) {
+                        match op {
+                            Op::Insert(x) => black_box(table.insert(x)),
+                            Op::Remove(x) => black_box(table.remove(&x)),
+                            Op::Contains(x) => black_box(table.contains(&x)),
+                        };
+                    }
+                    table
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.bench_function("std HashSet", |b| {
+        b.iter_batched(
+            || std_set.clone(),
+            |mut set| {
+                for &op in black_box(&ops) {
+                    match op {
+                        Op::Insert(x) => black_box(set.insert(x)),
+                        Op::Remove(x) => black_box(set.remove(&x)),
+                        Op::Contains(x) => black_box(set.contains(&x)),
+                    };
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("hashbrown HashSet", |b| {
+        b.iter_batched(
+            || hashbrown_set.clone(),
+            |mut set| {
+                for &op in black_box(&ops) {
+                    match op {
+                        Op::Insert(x) => black_box(set.insert(x)),
+                        Op::Remove(x) => black_box(set.remove(&x)),
+                        Op::Contains(x) => black_box(set.contains(&x)),
+                    };
+                }
+                set
+            },
+            BatchSize::LargeInput,
+        )
     });
+    group.finish();
 }
 
-criterion_group!(benches, criterion_insert);
-criterion_main!(benches);
\ No newline at end of file
+criterion_group!(benches, criterion_insert, criterion_contains, criterion_remove, criterion_mixed);
+criterion_main!(benches);
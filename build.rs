@@ -0,0 +1,21 @@
+fn main() {
+    #[cfg(feature = "cbindgen")]
+    generate_header();
+}
+
+/// Writes the C header to `OUT_DIR`. Builds never touch the source tree;
+/// to refresh the checked-in `include/cuckoo_rs.h`, build with
+/// `CUCKOO_RS_UPDATE_HEADER=1` set.
+#[cfg(feature = "cbindgen")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=CUCKOO_RS_UPDATE_HEADER");
+    let bindings = cbindgen::generate(&crate_dir).expect("unable to generate C bindings");
+    bindings.write_to_file(format!("{out_dir}/cuckoo_rs.h"));
+    if std::env::var_os("CUCKOO_RS_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(format!("{crate_dir}/include/cuckoo_rs.h"));
+    }
+}
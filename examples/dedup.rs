@@ -0,0 +1,95 @@
+//! Reads lines from stdin and prints each one the first time it is seen.
+//!
+//! Seen lines are remembered in a `ScalableCuckooFilter`, a growing
+//! stack of cuckoo filters, rather than in a set of the lines themselves,
+//! so memory grows with the number of distinct lines but not with their
+//! length. A filter can answer "seen" for a line it has never been given,
+//! so with probability of about `--fpr` per line a first occurrence is
+//! dropped. Duplicates are never printed.
+//!
+//! Run with `cargo run --release --example dedup -- [--capacity N] [--fpr P]`.
+
+use std::io::{self, BufRead, BufWriter, ErrorKind, Write};
+use std::process;
+
+use cuckoo_rs::filter::ScalableCuckooFilter;
+
+const DEFAULT_CAPACITY: usize = 1 << 16;
+const DEFAULT_FPR: f64 = 0.001;
+
+struct Args {
+    capacity: usize,
+    fpr: f64,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: dedup [--capacity N] [--fpr P]");
+    process::exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut args = Args { capacity: DEFAULT_CAPACITY, fpr: DEFAULT_FPR };
+    let mut argv = std::env::args().skip(1);
+    while let Some(flag) = argv.next() {
+        let value = argv.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "--capacity" => args.capacity = value.parse().unwrap_or_else(|_| usage()),
+            "--fpr" => args.fpr = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    if !(args.fpr > 0.0 && args.fpr < 1.0) {
+        usage();
+    }
+    args
+}
+
+fn dedup(input: impl BufRead, mut output: impl Write, filter: &mut ScalableCuckooFilter<str>) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if filter.insert(&line) {
+            writeln!(output, "{line}")?;
+        }
+    }
+    output.flush()
+}
+
+fn main() {
+    let args = parse_args();
+    let mut filter = ScalableCuckooFilter::new(args.capacity, args.fpr);
+    let stdout = BufWriter::new(io::stdout().lock());
+    match dedup(io::stdin().lock(), stdout, &mut filter) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            eprintln!("dedup: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_keeps_first_occurrences() {
+        let input: String = (0..20_000).map(|i| format!("line {}\n", i % 5_000)).collect();
+        let mut filter = ScalableCuckooFilter::new(64, 0.01);
+        let mut output = Vec::new();
+        dedup(input.as_bytes(), &mut output, &mut filter).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let printed: Vec<&str> = output.lines().collect();
+
+        // Every printed line is a first occurrence, in input order, and
+        // false positives dropped well under 1% of the distinct lines.
+        let mut last = None;
+        for line in &printed {
+            let i: usize = line.strip_prefix("line ").unwrap().parse().unwrap();
+            assert!(last.is_none_or(|last| i > last));
+            last = Some(i);
+        }
+        assert!(printed.len() > 4_950, "only {} lines printed", printed.len());
+        assert!(filter.layers() > 1);
+    }
+}
@@ -0,0 +1,77 @@
+//! Measures how full a `CuckooHashTable` gets before it has to grow, and
+//! how much displacement work inserts cost on the way.
+//!
+//! Run with `cargo run --release --example occupancy [trials]`.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use cuckoo_rs::cuckoo::CuckooHashTable;
+
+const INITIAL_CAPACITIES: [usize; 4] = [16, 1 << 10, 1 << 14, 1 << 18];
+
+/// Every trial inserts this many times the initial capacity, so each
+/// configuration goes through several resizes.
+const INSERTS_PER_BUCKET: usize = 8;
+
+struct Trial {
+    load_at_first_resize: f64,
+    displacements_per_insert: f64,
+    resizes: u64,
+}
+
+/// Key number `i` of trial `seed`: the pair run through the standard
+/// hasher with its fixed keys, so the example has no extra dependencies
+/// and every run draws the same keys. The table's own hash seeds still
+/// vary.
+fn key(seed: u64, i: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, i).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn run_trial(capacity: usize, seed: u64) -> Trial {
+    let mut table = CuckooHashTable::with_capacity(capacity);
+    let mut load_at_first_resize = None;
+    let inserts = capacity * INSERTS_PER_BUCKET;
+    for i in 0..inserts {
+        let before = table.len();
+        table.insert(key(seed, i));
+        if load_at_first_resize.is_none() && table.capacity() != capacity {
+            load_at_first_resize = Some(before as f64 / (2 * capacity) as f64);
+        }
+    }
+    let stats = table.stats();
+    Trial {
+        load_at_first_resize: load_at_first_resize.unwrap_or(f64::NAN),
+        displacements_per_insert: stats.displacements as f64 / inserts as f64,
+        resizes: stats.resizes,
+    }
+}
+
+fn main() {
+    let trials: u64 = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("trials must be a number"))
+        .unwrap_or(5);
+    println!(
+        "{:>16} {:>10} {:>16} {:>16} {:>22} {:>8}",
+        "initial capacity", "inserts", "min first-resize", "avg first-resize", "displacements/insert", "resizes"
+    );
+    for capacity in INITIAL_CAPACITIES {
+        let results: Vec<Trial> = (1..=trials).map(|seed| run_trial(capacity, seed)).collect();
+        let n = results.len() as f64;
+        let min_load = results.iter().map(|t| t.load_at_first_resize).fold(f64::INFINITY, f64::min);
+        let avg_load = results.iter().map(|t| t.load_at_first_resize).sum::<f64>() / n;
+        let displacements = results.iter().map(|t| t.displacements_per_insert).sum::<f64>() / n;
+        let resizes = results.iter().map(|t| t.resizes).sum::<u64>() as f64 / n;
+        println!(
+            "{:>16} {:>10} {:>15.1}% {:>15.1}% {:>22.3} {:>8.1}",
+            capacity,
+            capacity * INSERTS_PER_BUCKET,
+            100.0 * min_load,
+            100.0 * avg_load,
+            displacements,
+            resizes
+        );
+    }
+}
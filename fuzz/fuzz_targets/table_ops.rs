@@ -0,0 +1,47 @@
+#![no_main]
+
+use std::collections::HashSet;
+
+use arbitrary::Arbitrary;
+use cuckoo_rs::cuckoo::CuckooHashTable;
+use libfuzzer_sys::fuzz_target;
+
+/// A single operation applied to both the table and the reference set.
+/// Keys are `u16` so that inputs revisit the same keys often.
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert(u16),
+    Remove(u16),
+    Contains(u16),
+    ContainsOrInsert(u16),
+    /// Inserts a run of consecutive keys, which quickly exhausts the
+    /// displacement budget and forces the table to resize.
+    Fill { start: u16, len: u8 },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seeds: (u64, u64),
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut table = CuckooHashTable::with_seeds(input.seeds.0, input.seeds.1);
+    let mut set = HashSet::new();
+    for op in input.ops {
+        match op {
+            Op::Insert(x) => assert_eq!(table.insert(x), set.insert(x)),
+            Op::Remove(x) => assert_eq!(table.remove(&x), set.remove(&x)),
+            Op::Contains(x) => assert_eq!(table.contains(&x), set.contains(&x)),
+            Op::ContainsOrInsert(x) => assert_eq!(table.contains_or_insert(x), !set.insert(x)),
+            Op::Fill { start, len } => {
+                for x in (start..=u16::MAX).take(len as usize) {
+                    assert_eq!(table.insert(x), set.insert(x));
+                }
+            }
+        }
+    }
+    for x in &set {
+        assert!(table.contains(x), "lost {x}");
+    }
+});
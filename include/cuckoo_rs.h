@@ -0,0 +1,63 @@
+#ifndef CUCKOO_RS_H
+#define CUCKOO_RS_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Opaque handle to a cuckoo hash table of `u64` keys.
+ */
+typedef struct CuckooTable CuckooTable;
+
+/**
+ * Allocates a new, empty table.
+ */
+struct CuckooTable *cuckoo_table_new(void);
+
+/**
+ * Inserts `key` and returns `true` if it was not already present.
+ * Returns `false` if `table` is null.
+ *
+ * # Safety
+ *
+ * `table` must be null or a pointer returned by `cuckoo_table_new`
+ * that has not been freed yet.
+ */
+bool cuckoo_table_insert(struct CuckooTable *table, uint64_t key);
+
+/**
+ * Returns `true` if `key` is present. Returns `false` if `table` is null.
+ *
+ * # Safety
+ *
+ * `table` must be null or a pointer returned by `cuckoo_table_new`
+ * that has not been freed yet.
+ */
+bool cuckoo_table_contains(const struct CuckooTable *table, uint64_t key);
+
+/**
+ * Removes `key` and returns `true` if it was present. Returns `false`
+ * if `table` is null.
+ *
+ * # Safety
+ *
+ * `table` must be null or a pointer returned by `cuckoo_table_new`
+ * that has not been freed yet.
+ */
+bool cuckoo_table_remove(struct CuckooTable *table, uint64_t key);
+
+/**
+ * Releases a table. Passing null is a no-op.
+ *
+ * # Safety
+ *
+ * `table` must be null or a pointer returned by `cuckoo_table_new`,
+ * and it must not be used again after this call.
+ */
+void cuckoo_table_free(struct CuckooTable *table);
+
+#endif  /* CUCKOO_RS_H */
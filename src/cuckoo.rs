@@ -1,116 +1,748 @@
-use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::iter::{Chain, Flatten};
+use std::marker::PhantomData;
 
-const MAX_LOOP: u8 = 100;
+use std::sync::Arc;
+
+use crate::error::CuckooError;
+
+use self::handle::{next_epoch, SlotVersions};
+use self::hashing::Hashers;
+
+pub use self::handle::SlotHandle;
+pub use self::hashing::IdentityHasher;
+pub use self::resize::{GrowthFactor, ResizePolicy, Steps};
+pub use self::slots::Slots;
+pub use self::storage::{BoxedSlots, BucketStorage};
+
+mod handle;
+mod hashing;
+#[cfg(feature = "rayon")]
+mod par;
+mod resize;
+#[cfg(feature = "rand")]
+mod sample;
+mod set;
+mod slots;
+mod storage;
+
+/// Displacements an insertion may make before the table grows.
+const MAX_KICKS: usize = 200;
+const INIT_CAPACITY: usize = 16;
+const REMOVE_BATCH: usize = 16;
 
 /// `CuckooHashTable` consists of two sets of buckets where an item `x`
 /// can go to any of two buckets as long as there is an empty slot. The
 /// downside as compared to standard hash table is that it requires two
 /// independent hash functions.
-pub struct CuckooHashTable<T> {
-    buckets: [Vec<Option<T>>; 2],
+///
+/// Each bucket group is kept in a [`BucketStorage`]. The default,
+/// [`Slots`], is shared copy-on-write, so cloning a table is cheap and
+/// only the chunks that either copy later modifies get duplicated.
+pub struct CuckooHashTable<T, S = Slots<T>> {
+    buckets: [S; 2],
     size: usize,
     capacity: usize,
-    load_factor: f64,
-    hash1: DefaultHasher,
-    hash2: DefaultHasher,
+    resize_policy: Arc<dyn ResizePolicy>,
+    hashers: Hashers,
+    stats: TableStats,
+    /// The table's generation, from `next_epoch`. Replaced whenever the
+    /// table is rebuilt or bulk-emptied, which invalidates every
+    /// outstanding `SlotHandle`.
+    epoch: u64,
+    versions: SlotVersions,
+    marker: PhantomData<T>,
+}
+
+/// Counters describing the work a table has done since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// Number of times the table grew and rehashed its elements.
+    pub resizes: u64,
+    /// Number of elements moved out of their slot to make room for
+    /// another one, including the moves made while rehashing.
+    pub displacements: u64,
+    /// Number of elements `insert_or_evict` and `insert_bounded` dropped
+    /// from the table.
+    pub evictions: u64,
+}
+
+pub(crate) fn seeded_hasher(seed: u64) -> DefaultHasher {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(seed);
+    hasher
+}
+
+/// Advances a xorshift generator, whose state must not be zero. The kick
+/// chains of the filters and the disk index pick their victims with it:
+/// it only has to avoid cycling between the same slots, not be
+/// unpredictable.
+pub(crate) fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn random_hasher() -> DefaultHasher {
+    use std::hash::{BuildHasher, RandomState};
+    RandomState::new().build_hasher()
+}
+
+// `RandomState` has no entropy source on wasm32-unknown-unknown, so the
+// seed comes from `getrandom`, which uses `crypto.getRandomValues` there.
+#[cfg(feature = "wasm")]
+pub(crate) fn random_hasher() -> DefaultHasher {
+    let mut seed = [0u8; 8];
+    getrandom::getrandom(&mut seed).expect("failed to obtain a random seed");
+    seeded_hasher(u64::from_le_bytes(seed))
+}
+
+/// Returns the size of the two bucket groups of a table with `capacity`
+/// buckets each, or fails if that could never be allocated for elements
+/// of type `T`.
+fn check_capacity<T>(capacity: usize) -> Result<usize, CuckooError> {
+    let bytes = capacity
+        .checked_mul(2 * std::mem::size_of::<Option<T>>())
+        .ok_or(CuckooError::CapacityOverflow)?;
+    if bytes > isize::MAX as usize {
+        return Err(CuckooError::AllocationFailed { bytes });
+    }
+    Ok(bytes)
+}
+
+/// Hints the CPU to start loading `slot` into cache.
+#[inline]
+fn prefetch<S>(slot: &S) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: SSE is part of the x86_64 baseline, and a prefetch never
+    // faults or changes memory, whatever address it is given.
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>((slot as *const S).cast());
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = slot;
+}
+
+impl<T: Hash + Clone + Eq> Default for CuckooHashTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: Hash + Clone + Eq> CuckooHashTable<T> {
     pub fn new() -> Self {
-        let init_capacity: usize = 16;
-        let rs1 = RandomState::new();
-        let rs2 = RandomState::new();
-        let h1 = rs1.build_hasher();
-        let h2 = rs2.build_hasher();
+        Self::with_capacity(INIT_CAPACITY)
+    }
+
+    /// Creates an empty table with `capacity` buckets in each of its two
+    /// bucket groups. The table still grows past this once an insertion
+    /// cannot find a free slot.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_hashers(capacity, Hashers::Seeded(random_hasher(), random_hasher()))
+    }
+
+    /// Like `with_capacity`, but returns an error instead of aborting when
+    /// the bucket storage cannot be allocated.
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, CuckooError> {
+        Self::try_with_hashers(capacity, Hashers::Seeded(random_hasher(), random_hasher()))
+    }
+
+    /// Creates a table whose two hash functions are derived from the
+    /// given seeds instead of the process-wide random state. Tables built
+    /// with the same seeds place every element in the same buckets, and
+    /// this works on targets without an OS entropy source.
+    pub fn with_seeds(seed1: u64, seed2: u64) -> Self {
+        Self::with_capacity_and_seeds(INIT_CAPACITY, seed1, seed2)
+    }
+
+    /// Like `with_seeds`, but with `capacity` buckets per group.
+    pub(crate) fn with_capacity_and_seeds(capacity: usize, seed1: u64, seed2: u64) -> Self {
+        Self::with_hashers(capacity, Hashers::Seeded(seeded_hasher(seed1), seeded_hasher(seed2)))
+    }
+
+    /// Creates an empty table that uses the key itself as its hash, via
+    /// [`IdentityHasher`], instead of hashing it. The low 32 bits of the
+    /// key pick the bucket in the first group and the high 32 bits the
+    /// bucket in the second. Only use this when keys are already
+    /// uniformly random.
+    pub fn with_identity_hashing(capacity: usize) -> Self {
+        Self::with_hashers(capacity, Hashers::Identity)
+    }
+
+    /// Creates an empty table that derives both of an element's buckets
+    /// from one 128-bit hash instead of running two hashers over it. Keys
+    /// are hashed once with randomly keyed SipHash-1-3 with 128-bit
+    /// output; the low half picks the bucket in the first group and the
+    /// high half the bucket in the second.
+    pub fn with_wide_hashing(capacity: usize) -> Self {
+        Self::with_hashers(capacity, Hashers::wide(random_hasher().finish(), random_hasher().finish()))
+    }
+
+    /// Like `with_wide_hashing`, but derives the hash key from `seed`
+    /// instead of the process-wide random state, as `with_seeds` does for
+    /// two hashers.
+    pub fn with_wide_seed(seed: u64) -> Self {
+        Self::with_hashers(INIT_CAPACITY, Hashers::wide(seed, seeded_hasher(seed).finish()))
+    }
+
+    /// Returns a read-only copy of the table as it is now. The snapshot
+    /// shares bucket storage with the table; later writes to the table
+    /// copy the chunks they touch instead of changing what the snapshot
+    /// sees. Snapshots are `Send` and `Sync` when `T` is, so they can be
+    /// handed to reader threads while the writer keeps going.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot { table: self.clone() }
+    }
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Like `with_capacity`, for a table whose bucket groups are kept in
+    /// storage of type `S` rather than the default [`Slots`].
+    pub fn with_storage(capacity: usize) -> Self {
+        Self::with_hashers(capacity, Hashers::Seeded(random_hasher(), random_hasher()))
+    }
+
+    fn with_hashers(capacity: usize, hashers: Hashers) -> Self {
+        let capacity = capacity.max(1);
+        Self::with_buckets([S::with_len(capacity), S::with_len(capacity)], hashers)
+    }
+
+    fn try_with_hashers(capacity: usize, hashers: Hashers) -> Result<Self, CuckooError> {
+        let capacity = capacity.max(1);
+        let bytes = check_capacity::<T>(capacity)?;
+        let allocate = || S::try_with_len(capacity).map_err(|_| CuckooError::AllocationFailed { bytes });
+        Ok(Self::with_buckets([allocate()?, allocate()?], hashers))
+    }
+
+    fn with_buckets(buckets: [S; 2], hashers: Hashers) -> Self {
+        let capacity = buckets[0].len();
         CuckooHashTable {
-            buckets: [vec![None; init_capacity], vec![None; init_capacity]],
-            capacity: init_capacity,
+            buckets,
+            capacity,
             size: 0,
-            load_factor: 0.2,
-            hash1: h1,
-            hash2: h2,
+            resize_policy: Arc::new(GrowthFactor::default()),
+            hashers,
+            stats: TableStats::default(),
+            epoch: next_epoch(),
+            versions: SlotVersions::default(),
+            marker: PhantomData,
         }
     }
 
-    fn h1(&self, x: &T) -> usize {
-        let mut hasher1 = self.hash1.clone();
-        x.hash(&mut hasher1);
-        let h1 = hasher1.finish() as usize;
-        h1 % self.buckets[0].len()
+    /// Returns the number of elements in the table.
+    pub fn len(&self) -> usize {
+        self.size
     }
 
-    fn h2(&self, x: &T) -> usize {
-        let mut hasher2 = self.hash2.clone();
-        x.hash(&mut hasher2);
-        let h2 = hasher2.finish() as usize;
-        h2 % self.buckets[1].len()
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
     }
 
-    pub fn contains(&self, x: &T) -> bool {
-        let b1 = self.h1(x);
-        let b2 = self.h2(x);
-        self.buckets[0][b1].as_ref() == Some(x) ||
-            self.buckets[1][b2].as_ref() == Some(x)
+    /// Returns the number of buckets in each of the two bucket groups.
+    ///
+    /// Unlike `HashSet::capacity`, this is not how many elements fit
+    /// before the table grows; see `len_before_resize` for that.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns how many elements the table can hold before the resize
+    /// policy's maximum load forces it to grow, which is what
+    /// `HashSet::capacity` reports. An insertion whose displacement chain
+    /// runs out can still grow the table earlier.
+    pub fn len_before_resize(&self) -> usize {
+        (self.resize_policy.max_load() * (2 * self.capacity) as f64) as usize
+    }
+
+    pub fn stats(&self) -> TableStats {
+        self.stats
+    }
+
+    /// Summarises how the occupied slots are laid out in each bucket
+    /// group. Long runs of filled slots mean the hash functions cluster
+    /// for this workload, which makes displacement chains fail early
+    /// and the table resize at a low load.
+    pub fn occupancy_report(&self) -> OccupancyReport {
+        OccupancyReport {
+            groups: [
+                GroupOccupancy::measure(&self.buckets[0]),
+                GroupOccupancy::measure(&self.buckets[1]),
+            ],
+        }
+    }
+
+    /// Returns an iterator over the elements in slot order, which is
+    /// effectively arbitrary.
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            inner: self.buckets[0].iter().chain(self.buckets[1].iter()).flatten(),
+            remaining: self.size,
+        }
+    }
+
+    /// Returns a cursor positioned before the first occupied slot, which
+    /// can remove or replace elements while walking the table.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, S> {
+        CursorMut { table: self, position: None, pending: Vec::new() }
     }
 
-    pub fn remove(&mut self, x: &T) -> bool {
+    fn h1<Q: Hash + ?Sized>(&self, x: &Q) -> usize {
+        self.hashers.hash(0, x) as usize % self.buckets[0].len()
+    }
+
+    fn h2<Q: Hash + ?Sized>(&self, x: &Q) -> usize {
+        self.hashers.hash(1, x) as usize % self.buckets[1].len()
+    }
+
+    /// Candidate buckets of `x` in both groups. Prefer this over `h1` and
+    /// `h2` when both are needed, so wide hashing passes over `x` once.
+    fn indices<Q: Hash + ?Sized>(&self, x: &Q) -> (usize, usize) {
+        let (hash1, hash2) = self.hashers.hash_pair(x);
+        (hash1 as usize % self.buckets[0].len(), hash2 as usize % self.buckets[1].len())
+    }
+
+    /// Returns `true` if the table holds `x`. As with `HashSet`, `x` may
+    /// be any borrowed form of the element type whose `Hash` and `Eq`
+    /// agree with the element's.
+    pub fn contains<Q: Hash + Eq + ?Sized>(&self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.find(x).is_some()
+    }
+
+    /// Returns the bucket group and bucket that hold `x`, if any.
+    fn find<Q: Hash + Eq + ?Sized>(&self, x: &Q) -> Option<(usize, usize)>
+    where
+        T: Borrow<Q>,
+    {
+        let (b1, b2) = self.indices(x);
+        if self.buckets[0][b1].as_ref().map(Borrow::borrow) == Some(x) {
+            Some((0, b1))
+        } else if self.buckets[1][b2].as_ref().map(Borrow::borrow) == Some(x) {
+            Some((1, b2))
+        } else {
+            None
+        }
+    }
+
+    /// Looks an element up without hashing an element: `key` must hash
+    /// the way the element being looked for does, and `eq` decides which
+    /// of the two candidates, if either, it is. This is for elements
+    /// whose equality depends on data outside the table.
+    pub(crate) fn find_with<Q: Hash + ?Sized>(&self, key: &Q, mut eq: impl FnMut(&T) -> bool) -> Option<&T> {
+        let (b1, b2) = self.indices(key);
+        [&self.buckets[0][b1], &self.buckets[1][b2]].into_iter().flatten().find(|x| eq(x))
+    }
+
+    /// Returns the candidate bucket of `x` in the first and second bucket
+    /// group. Both indices are below `capacity()` and change whenever the
+    /// table resizes or rehashes.
+    pub fn locations(&self, x: &T) -> (usize, usize) {
+        self.indices(x)
+    }
+
+    /// Describes where `x` would live: its candidate bucket in each group
+    /// and whatever currently occupies those buckets.
+    pub fn explain(&self, x: &T) -> Explanation<'_, T> {
+        let (b0, b1) = self.indices(x);
+        let candidates = [b0, b1];
+        let occupants = [
+            self.buckets[0][candidates[0]].as_ref(),
+            self.buckets[1][candidates[1]].as_ref(),
+        ];
+        let found = if occupants[0] == Some(x) {
+            Some(0)
+        } else if occupants[1] == Some(x) {
+            Some(1)
+        } else {
+            None
+        };
+        Explanation { candidates, occupants, found }
+    }
+
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
         let b1 = self.h1(x);
-        if self.buckets[0][b1].as_ref() == Some(x) {
+        if self.buckets[0][b1].as_ref().map(Borrow::borrow) == Some(x) {
             self.buckets[0][b1] = None;
+            self.versions.bump(0, b1);
             self.size -= 1;
             return true;
         }
         let b2 = self.h2(x);
-        if self.buckets[1][b2].as_ref() == Some(x) {
+        if self.buckets[1][b2].as_ref().map(Borrow::borrow) == Some(x) {
             self.buckets[1][b2] = None;
+            self.versions.bump(1, b2);
             self.size -= 1;
             return true;
         }
-        return false;
+        false
+    }
+
+    /// Removes every element yielded by `items` and returns how many were
+    /// present. Keys are hashed in batches and their candidate slots are
+    /// prefetched before any of them is compared, so the cache misses of
+    /// a batch overlap instead of being paid one key at a time.
+    pub fn remove_many<I>(&mut self, items: I) -> usize
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        let mut removed = 0;
+        let mut batch = Vec::with_capacity(REMOVE_BATCH);
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            batch.extend(items.by_ref().take(REMOVE_BATCH).map(|item| {
+                let (b1, b2) = self.indices(item.borrow());
+                prefetch(&self.buckets[0][b1]);
+                prefetch(&self.buckets[1][b2]);
+                (item, b1, b2)
+            }));
+            for (item, b1, b2) in batch.drain(..) {
+                let x = item.borrow();
+                if self.buckets[0][b1].as_ref() == Some(x) {
+                    self.buckets[0][b1] = None;
+                    self.versions.bump(0, b1);
+                } else if self.buckets[1][b2].as_ref() == Some(x) {
+                    self.buckets[1][b2] = None;
+                    self.versions.bump(1, b2);
+                } else {
+                    continue;
+                }
+                self.size -= 1;
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Keeps only the elements for which `f` returns `true`.
+    ///
+    /// Slots to clear are found through a shared borrow first, so storage
+    /// shared with a snapshot or clone is only copied where elements are
+    /// actually removed.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        for group in 0..2 {
+            let doomed: Vec<usize> = self.buckets[group]
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| slot.as_ref().is_some_and(|x| !f(x)))
+                .map(|(index, _)| index)
+                .collect();
+            for index in doomed {
+                self.buckets[group][index] = None;
+                self.versions.bump(group, index);
+                self.size -= 1;
+            }
+        }
+    }
+
+    /// Removes every element that is not also in `other`, in place.
+    pub fn intersect_with(&mut self, other: &CuckooHashTable<T, S>) {
+        self.retain(|x| other.contains(x));
+    }
+
+    /// Removes every element that is also in `other`, in place. Whichever
+    /// of the two tables is smaller is the one that gets walked.
+    pub fn subtract(&mut self, other: &CuckooHashTable<T, S>) {
+        if other.len() < self.len() {
+            self.remove_many(other.iter());
+        } else {
+            self.retain(|x| !other.contains(x));
+        }
+    }
+
+    /// Moves every element for which `pred` returns `true` into a new
+    /// table and returns it. The new table has the same capacity and hash
+    /// functions as this one, so each element moves to the same slot it
+    /// occupied here and nothing is rehashed.
+    pub fn split_off<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> CuckooHashTable<T, S> {
+        let mut split = self.empty_like(self.capacity);
+        for (group, bucket) in self.buckets.iter_mut().enumerate() {
+            for (index, slot) in bucket.iter_mut().enumerate() {
+                if slot.as_ref().is_some_and(&mut pred) {
+                    split.buckets[group][index] = slot.take();
+                    self.versions.bump(group, index);
+                    split.size += 1;
+                    self.size -= 1;
+                }
+            }
+        }
+        split
+    }
+
+    /// Consumes the table and distributes its elements over `n` new
+    /// tables, putting each element in the table at index `shard(x) % n`.
+    /// Elements are moved rather than cloned. The new tables share this
+    /// table's hash functions and resize policy, and each one reserves
+    /// room for an even share of the elements up front.
+    ///
+    /// The table caches no hashes, so every element is hashed again to
+    /// find its buckets in its new table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn partition_into<F: FnMut(&T) -> usize>(self, n: usize, mut shard: F) -> Vec<CuckooHashTable<T, S>> {
+        assert!(n > 0, "cannot partition into zero tables");
+        let mut shards: Vec<_> = (0..n)
+            .map(|_| self.empty_like(self.capacity_for(self.size.div_ceil(n))))
+            .collect();
+        for x in self {
+            let index = shard(&x) % n;
+            shards[index].insert(x);
+        }
+        shards
+    }
+
+    /// Grows the table, if needed, so that `additional` more elements fit
+    /// while at most half of the slots are occupied, or less if the
+    /// resize policy's maximum load is lower. Past one half two-choice
+    /// cuckoo hashing is likely to exhaust its displacement budget and
+    /// resize anyway. The capacity follows the resize policy's steps but
+    /// growth happens in a single rehash.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed_capacity = self.capacity_for(self.size.saturating_add(additional));
+        if needed_capacity <= self.capacity {
+            return;
+        }
+        let mut new_capacity = self.capacity;
+        while new_capacity < needed_capacity {
+            new_capacity = self.next_capacity(new_capacity);
+        }
+        self.rehash_to(new_capacity);
+    }
+
+    /// Returns the capacity at which `len` elements occupy at most half of
+    /// the slots, or less if the resize policy's maximum load is lower.
+    fn capacity_for(&self, len: usize) -> usize {
+        let load = self.resize_policy.max_load().min(0.5);
+        (len as f64 / (2.0 * load)).ceil() as usize
+    }
+
+    /// Like `reserve`, but returns an error instead of panicking or
+    /// aborting when the required capacity cannot be represented or
+    /// allocated. The table is unchanged on error; to make sure of that,
+    /// growing clones the elements into the new storage instead of
+    /// moving them.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), CuckooError> {
+        let needed = self.size.checked_add(additional).ok_or(CuckooError::CapacityOverflow)?;
+        let load = self.resize_policy.max_load().min(0.5);
+        let needed_capacity = needed as f64 / (2.0 * load);
+        if needed_capacity >= usize::MAX as f64 {
+            return Err(CuckooError::CapacityOverflow);
+        }
+        let needed_capacity = needed_capacity.ceil() as usize;
+        if needed_capacity > self.capacity {
+            let mut new_capacity = self.capacity;
+            while new_capacity < needed_capacity {
+                new_capacity = self.next_capacity(new_capacity);
+            }
+            self.try_rehash_to(new_capacity)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the policy that decides how far the table grows and how
+    /// full it may get. Takes effect from the next insertion.
+    pub fn with_resize_policy<P: ResizePolicy + 'static>(mut self, policy: P) -> Self {
+        self.resize_policy = Arc::new(policy);
+        self
+    }
+
+    /// Moves every element of `other` into this table. Room for both
+    /// tables is reserved up front, so at most one rehash of `self` takes
+    /// place, and elements are moved rather than cloned.
+    pub fn absorb(&mut self, other: CuckooHashTable<T, S>) {
+        self.reserve(other.len());
+        for x in other {
+            self.insert(x);
+        }
     }
 
     pub fn insert(&mut self, x: T) -> bool {
+        !self.contains_or_insert(x)
+    }
+
+    /// Like `insert`, but returns an error instead of aborting when the
+    /// table has to grow and the larger storage cannot be allocated. This
+    /// holds however many times placing `x` makes the table grow, and the
+    /// table is unchanged on error. As with `try_reserve`, growing clones
+    /// the elements instead of moving them.
+    pub fn try_insert(&mut self, x: T) -> Result<bool, CuckooError> {
         if self.contains(&x) {
+            return Ok(false);
+        }
+        self.try_insert_absent(x)?;
+        Ok(true)
+    }
+
+    /// Reports whether `x` was already present and inserts it if it
+    /// was not. Unlike calling `contains` followed by `insert`, the
+    /// candidate buckets are hashed and probed only once, which makes
+    /// this the primitive to use for streaming deduplication.
+    pub fn contains_or_insert(&mut self, x: T) -> bool {
+        let (b0, b1) = self.indices(&x);
+        let slot0 = self.buckets[0][b0].as_ref();
+        let slot1 = self.buckets[1][b1].as_ref();
+        if slot0 == Some(&x) || slot1 == Some(&x) {
+            return true;
+        }
+        let (free0, free1) = (slot0.is_none(), slot1.is_none());
+        if self.exceeds_max_load(self.size + 1) {
+            self.resize_and_rehash();
+            self.insert(x);
             return false;
         }
-        let b0 = self.h1(&x);
-        if self.buckets[0][b0].is_none() {
+        if free0 {
             self.insert_into_slot(0, b0, x);
-            return true;
+            return false;
         }
-        let b1 = self.h2(&x);
-        if self.buckets[1][b1].is_none() {
+        if free1 {
             self.insert_into_slot(1, b1, x);
-            return true;
+            return false;
         }
         // We reach here when we cannot insert the
         // key straightaway to either of the slots.
         // In this case, we have to move things around
         // a bit to make space for it until we find some
         // space or rehash the elements with a larger table.
+        if let Some(homeless) = self.displace(x, MAX_KICKS, |_| {}) {
+            // If we are here, it means that we don't have enough
+            // slots to insert. Hence, we need to rehash and retry
+            // inserting into the table.
+            self.resize_and_rehash();
+            self.insert(homeless);
+        }
+        false
+    }
+
+    /// Inserts `x` without ever growing the table. If the displacement
+    /// chain runs out, the element that fell off its end is evicted and
+    /// returned instead; this may be `x` itself. Returns `None` if `x`
+    /// was placed without evicting anything or was already present.
+    pub fn insert_or_evict(&mut self, x: T) -> Option<T> {
+        self.insert_bounded(x, MAX_KICKS)
+    }
+
+    /// Like `insert_or_evict`, but gives up after at most `max_kicks`
+    /// displacements. This bounds the work of a single insertion for
+    /// callers with latency limits; with `max_kicks` of zero, `x` is only
+    /// placed if one of its own buckets is free.
+    pub fn insert_bounded(&mut self, x: T, max_kicks: usize) -> Option<T> {
+        self.insert_bounded_with(x, max_kicks, |_| {})
+    }
+
+    /// Like `insert_bounded`, but also pushes a clone of every element the
+    /// chain moved to a different slot onto `displaced`, in the order they
+    /// were moved. Elements may appear more than once.
+    pub fn insert_bounded_tracked(&mut self, x: T, max_kicks: usize, displaced: &mut Vec<T>) -> Option<T> {
+        self.insert_bounded_with(x, max_kicks, |moved| displaced.push(moved.clone()))
+    }
+
+    /// Like `insert_bounded`, but never evicts: if the displacement chain
+    /// runs out, every move it made is undone and `InsertionFailed` is
+    /// returned, so the table holds exactly the elements it held before
+    /// and `x` is dropped. Returns `Ok(false)` if `x` was already present.
+    pub fn try_insert_bounded(&mut self, x: T, max_kicks: usize) -> Result<bool, CuckooError> {
+        if self.contains(&x) {
+            return Ok(false);
+        }
+        match self.place(x, max_kicks) {
+            Ok(()) => Ok(true),
+            Err(_) => Err(CuckooError::InsertionFailed { displacements: max_kicks as u64 }),
+        }
+    }
+
+    fn insert_bounded_with(&mut self, x: T, max_kicks: usize, on_kick: impl FnMut(&T)) -> Option<T> {
+        let (b0, b1) = self.indices(&x);
+        let slot0 = self.buckets[0][b0].as_ref();
+        let slot1 = self.buckets[1][b1].as_ref();
+        if slot0 == Some(&x) || slot1 == Some(&x) {
+            return None;
+        }
+        if slot0.is_none() {
+            self.insert_into_slot(0, b0, x);
+            return None;
+        }
+        if slot1.is_none() {
+            self.insert_into_slot(1, b1, x);
+            return None;
+        }
+        let evicted = self.displace(x, max_kicks, on_kick);
+        if evicted.is_some() {
+            self.stats.evictions += 1;
+        }
+        evicted
+    }
+
+    /// Runs the kick chain for `x`, which must not be in the table, moving
+    /// at most `max_kicks` elements and passing each one to `on_kick`.
+    /// Returns the element left without a slot when the budget runs out,
+    /// if any.
+    fn displace(&mut self, x: T, max_kicks: usize, mut on_kick: impl FnMut(&T)) -> Option<T> {
         let mut current = x;
-        for _ in 0..MAX_LOOP {
-            let b1 = self.h1(&current);
-            if self.buckets[0][b1].is_none() {
-                self.insert_into_slot(0, b1, current);
-                return true;
+        let mut group = 0;
+        for kicks in 0..=max_kicks {
+            let bucket = if group == 0 { self.h1(&current) } else { self.h2(&current) };
+            if self.buckets[group][bucket].is_none() {
+                self.insert_into_slot(group, bucket, current);
+                return None;
             }
-            // It is safe to expect this to be Some(x) because we
-            // have already performed the None check in the previous
-            // step, and we will never reach here in that case.
-            current = self.buckets[0][b1].replace(current).expect("must not be None");
-            let b2 = self.h2(&current);
-            if self.buckets[1][b2].is_none() {
-                self.insert_into_slot(1, b2, current);
-                return true;
+            if kicks == max_kicks {
+                break;
             }
+            // It is safe to expect this to be Some(x) because we
+            // have already performed the None check above. Alternate
+            // groups on every kick; otherwise the next round finds the
+            // element we just placed and the two keep swapping places.
+            current = self.buckets[group][bucket].replace(current).expect("must not be None");
+            on_kick(&current);
+            self.versions.bump(group, bucket);
+            self.stats.displacements += 1;
+            group ^= 1;
+        }
+        Some(current)
+    }
+
+    /// Puts `x`, which must not be in the table, into a free candidate
+    /// bucket or at the end of a kick chain of at most `max_kicks` moves.
+    /// If the chain runs out, its moves are undone and `x` is handed back.
+    fn place(&mut self, x: T, max_kicks: usize) -> Result<(), T> {
+        let (b0, b1) = self.indices(&x);
+        if self.buckets[0][b0].is_none() {
+            self.insert_into_slot(0, b0, x);
+            return Ok(());
+        }
+        if self.buckets[1][b1].is_none() {
+            self.insert_into_slot(1, b1, x);
+            return Ok(());
+        }
+        match self.displace(x, max_kicks, |_| {}) {
+            None => Ok(()),
+            Some(homeless) => Err(self.undo_kicks(homeless, max_kicks)),
+        }
+    }
+
+    /// Reverses a kick chain that made `kicks` moves and left `current`
+    /// without a slot, returning the element the chain started with.
+    /// Every element evicted by kick `k` sat in its own bucket of group
+    /// `k % 2`, so walking back only needs the elements' hashes.
+    fn undo_kicks(&mut self, mut current: T, kicks: usize) -> T {
+        for kick in (0..kicks).rev() {
+            let group = kick % 2;
+            let bucket = if group == 0 { self.h1(&current) } else { self.h2(&current) };
+            current = self.buckets[group][bucket].replace(current).expect("kicked from an occupied slot");
         }
-        // If we are here, it means that we don't have enough
-        // slots to insert. Hence, we need to rehash and retry
-        // inserting into the table.
-        self.resize_and_rehash();
-        self.insert(current);
-        return true;
+        current
     }
 
     #[inline]
@@ -119,16 +751,80 @@ impl<T: Hash + Clone + Eq> CuckooHashTable<T> {
         self.size += 1;
     }
 
+    /// Returns an empty table with the given capacity that shares this
+    /// table's hash functions. Handles issued by this table are not valid
+    /// in it.
+    pub(crate) fn empty_like(&self, capacity: usize) -> CuckooHashTable<T, S> {
+        self.succeeded_by(Self::with_hashers(capacity, self.hashers.clone()))
+    }
+
+    /// Like `empty_like`, but reports a failed allocation.
+    fn try_empty_like(&self, capacity: usize) -> Result<CuckooHashTable<T, S>, CuckooError> {
+        Ok(self.succeeded_by(Self::try_with_hashers(capacity, self.hashers.clone())?))
+    }
+
+    /// Gives the freshly built `table` this table's resize policy and
+    /// slot version tracking.
+    fn succeeded_by(&self, mut table: CuckooHashTable<T, S>) -> CuckooHashTable<T, S> {
+        table.resize_policy = Arc::clone(&self.resize_policy);
+        table.versions = self.versions.like(table.capacity);
+        table
+    }
+
     fn resize_and_rehash(&mut self) {
-        let new_capacity = self.capacity * 2;
-        let mut resized = CuckooHashTable{
-            buckets: [vec![None; new_capacity], vec![None; new_capacity]],
-            size: 0,
-            load_factor: self.load_factor,
-            capacity: new_capacity,
-            hash1: self.hash1.clone(),
-            hash2: self.hash2.clone(),
+        self.rehash_to(self.next_capacity(self.capacity));
+    }
+
+    fn next_capacity(&self, capacity: usize) -> usize {
+        self.resize_policy.next_capacity(capacity).max(capacity.saturating_add(1))
+    }
+
+    /// Returns `true` if holding `size` elements would put the table over
+    /// the resize policy's maximum load.
+    fn exceeds_max_load(&self, size: usize) -> bool {
+        size as f64 > self.resize_policy.max_load() * (2 * self.capacity) as f64
+    }
+
+    /// Rebuilds the table at its current capacity with freshly drawn hash
+    /// functions. Use this when `occupancy_report` shows clustering, or
+    /// when the hash functions may have been learned by an attacker who
+    /// then feeds colliding keys. A table created with
+    /// `with_identity_hashing` has nothing to reseed and is rebuilt with
+    /// the same placement.
+    pub fn rehash(&mut self) {
+        let hashers = match self.hashers {
+            Hashers::Seeded(..) => Hashers::Seeded(random_hasher(), random_hasher()),
+            Hashers::Identity => Hashers::Identity,
+            Hashers::Wide(_) => Hashers::wide(random_hasher().finish(), random_hasher().finish()),
         };
+        self.rehash_with_hashers(hashers);
+    }
+
+    /// Like `rehash`, but derives the new hash functions from the given
+    /// seeds, as `with_seeds` does. This also switches a table created
+    /// with `with_identity_hashing` or `with_wide_hashing` to seeded
+    /// hashing.
+    pub fn rehash_with_seeds(&mut self, seed1: u64, seed2: u64) {
+        self.rehash_with_hashers(Hashers::Seeded(seeded_hasher(seed1), seeded_hasher(seed2)));
+    }
+
+    fn rehash_with_hashers(&mut self, hashers: Hashers) {
+        let mut rehashed = Self::with_hashers(self.capacity, hashers);
+        rehashed.resize_policy = Arc::clone(&self.resize_policy);
+        rehashed.stats = self.stats;
+        rehashed.versions = self.versions.like(self.capacity);
+        for bucket in &mut self.buckets {
+            for item in bucket.iter_mut().filter(|x| x.is_some()) {
+                rehashed.insert(item.take().expect("unexpectedly none"));
+            }
+        }
+        *self = rehashed;
+    }
+
+    fn rehash_to(&mut self, new_capacity: usize) {
+        let mut resized = self.empty_like(new_capacity);
+        resized.stats = self.stats;
+        resized.stats.resizes += 1;
         for bucket in &mut self.buckets {
             for item in bucket.iter_mut().filter(|x| x.is_some()) {
                 resized.insert(item.take().expect("unexpectedly none"));
@@ -136,6 +832,469 @@ impl<T: Hash + Clone + Eq> CuckooHashTable<T> {
         }
         *self = resized;
     }
+
+    /// Like `rehash_to`, but allocates fallibly and leaves the table as it
+    /// was on error. Elements are cloned rather than moved so that there
+    /// is still a whole table to fall back on.
+    fn try_rehash_to(&mut self, new_capacity: usize) -> Result<(), CuckooError> {
+        let mut resized = self.try_empty_like(new_capacity)?;
+        resized.stats = self.stats;
+        resized.stats.resizes += 1;
+        for x in self.iter() {
+            resized.try_insert_absent(x.clone())?;
+        }
+        *self = resized;
+        Ok(())
+    }
+
+    /// Places `x`, which must not be in the table, growing through
+    /// `try_rehash_to` as often as that takes.
+    fn try_insert_absent(&mut self, mut x: T) -> Result<(), CuckooError> {
+        if self.exceeds_max_load(self.size + 1) {
+            self.try_rehash_to(self.next_capacity(self.capacity))?;
+        }
+        while let Err(homeless) = self.place(x, MAX_KICKS) {
+            x = homeless;
+            self.try_rehash_to(self.next_capacity(self.capacity))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Debug, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Returns a view whose `Debug` output lists every slot of both
+    /// bucket groups in order, with `_` for empty slots.
+    pub fn dump_slots(&self) -> SlotDump<'_, T, S> {
+        SlotDump { buckets: &self.buckets, marker: PhantomData }
+    }
+}
+
+#[cfg(feature = "testing")]
+impl<T: Hash + Clone + Eq + fmt::Debug, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Checks the table's internal invariants and describes the first one
+    /// that does not hold: both bucket groups have `capacity()` slots,
+    /// `len()` matches the number of occupied slots, and every element
+    /// sits in one of its two candidate buckets and only in one.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        for (group, bucket) in self.buckets.iter().enumerate() {
+            if bucket.len() != self.capacity {
+                return Err(format!("group {group} has {} slots, capacity is {}", bucket.len(), self.capacity));
+            }
+        }
+        let mut occupied = 0;
+        for (group, bucket) in self.buckets.iter().enumerate() {
+            for (index, slot) in bucket.iter().enumerate() {
+                let Some(x) = slot else { continue };
+                occupied += 1;
+                let (b0, b1) = self.indices(x);
+                let candidate = [b0, b1][group];
+                if index != candidate {
+                    return Err(format!("{x:?} is in group {group} bucket {index}, expected bucket {candidate}"));
+                }
+                if group == 0 && self.buckets[1][b1].as_ref() == Some(x) {
+                    return Err(format!("{x:?} is stored in both groups"));
+                }
+            }
+        }
+        if occupied != self.size {
+            return Err(format!("{occupied} slots are occupied but len is {}", self.size));
+        }
+        Ok(())
+    }
+}
+
+/// A clone is a table of its own with a fresh generation, so handles
+/// issued by either table are not valid in the other.
+impl<T: Clone, S: Clone> Clone for CuckooHashTable<T, S> {
+    fn clone(&self) -> Self {
+        CuckooHashTable {
+            buckets: self.buckets.clone(),
+            size: self.size,
+            capacity: self.capacity,
+            resize_policy: Arc::clone(&self.resize_policy),
+            hashers: self.hashers.clone(),
+            stats: self.stats,
+            epoch: next_epoch(),
+            versions: self.versions.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Debug, S: BucketStorage<T>> fmt::Debug for CuckooHashTable<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.buckets.iter().flat_map(|group| group.iter()).flatten()).finish()
+    }
+}
+
+/// Immutable view of a table, created by [`CuckooHashTable::snapshot`].
+#[derive(Clone)]
+pub struct Snapshot<T> {
+    table: CuckooHashTable<T>,
+}
+
+impl<T: Hash + Clone + Eq> Snapshot<T> {
+    pub fn contains<Q: Hash + Eq + ?Sized>(&self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.table.contains(x)
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.table.iter()
+    }
+}
+
+impl<T: fmt::Debug + Clone> fmt::Debug for Snapshot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.table.fmt(f)
+    }
+}
+
+/// A cursor over the occupied slots of a table, created by
+/// [`CuckooHashTable::cursor_mut`].
+///
+/// A replacement that hashes to the current slot is stored in place. Any
+/// other replacement is held back and inserted when the cursor is
+/// dropped, so it is never visited by the traversal and cannot trigger a
+/// resize underneath it.
+pub struct CursorMut<'a, T: Hash + Clone + Eq, S: BucketStorage<T> = Slots<T>> {
+    table: &'a mut CuckooHashTable<T, S>,
+    /// Bucket group and index of the current slot.
+    position: Option<(usize, usize)>,
+    pending: Vec<T>,
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> CursorMut<'_, T, S> {
+    /// Advances to the next occupied slot and returns its element, or
+    /// `None` once the whole table has been walked.
+    pub fn move_next(&mut self) -> Option<&T> {
+        let (mut group, mut index) = match self.position {
+            None => (0, 0),
+            Some((group, index)) => (group, index + 1),
+        };
+        while group < 2 {
+            let slots = &self.table.buckets[group];
+            while index < slots.len() {
+                if slots[index].is_some() {
+                    self.position = Some((group, index));
+                    return slots[index].as_ref();
+                }
+                index += 1;
+            }
+            group += 1;
+            index = 0;
+        }
+        self.position = Some((1, self.table.capacity));
+        None
+    }
+
+    /// Returns the element under the cursor, if any.
+    pub fn current(&self) -> Option<&T> {
+        let (group, index) = self.position?;
+        if index >= self.table.capacity {
+            return None;
+        }
+        self.table.buckets[group][index].as_ref()
+    }
+
+    /// Removes and returns the element under the cursor. The cursor stays
+    /// on the now empty slot until the next `move_next`.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let (group, index) = self.position?;
+        if index >= self.table.capacity || self.table.buckets[group][index].is_none() {
+            return None;
+        }
+        self.table.size -= 1;
+        self.table.versions.bump(group, index);
+        self.table.buckets[group][index].take()
+    }
+
+    /// Replaces the element under the cursor with `x` and returns the old
+    /// element. If `x` does not hash to the current slot it is inserted
+    /// when the cursor is dropped; if `x` is already in the table the
+    /// old element is simply removed.
+    pub fn replace_current(&mut self, x: T) -> Option<T> {
+        let (group, index) = self.position?;
+        if index >= self.table.capacity || self.table.buckets[group][index].is_none() {
+            return None;
+        }
+        if self.table.buckets[group][index].as_ref() == Some(&x) {
+            return self.table.buckets[group][index].replace(x);
+        }
+        let old = self.remove_current();
+        if self.table.contains(&x) {
+            return old;
+        }
+        let home = if group == 0 { self.table.h1(&x) } else { self.table.h2(&x) };
+        if home == index {
+            self.table.insert_into_slot(group, index, x);
+        } else {
+            self.pending.push(x);
+        }
+        old
+    }
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> Drop for CursorMut<'_, T, S> {
+    fn drop(&mut self) {
+        for x in self.pending.drain(..) {
+            self.table.insert(x);
+        }
+    }
+}
+
+/// Placement of a single key, created by [`CuckooHashTable::explain`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Explanation<'a, T> {
+    /// Candidate bucket of the key in group 0 and group 1.
+    pub candidates: [usize; 2],
+    /// Current occupants of the two candidate buckets.
+    pub occupants: [Option<&'a T>; 2],
+    found: Option<usize>,
+}
+
+impl<T> Explanation<'_, T> {
+    /// Returns the bucket group the key was found in, if it is present.
+    pub fn found_in(&self) -> Option<usize> {
+        self.found
+    }
+
+    /// Returns `true` if neither candidate bucket has room, so inserting
+    /// the key would have to displace an occupant.
+    pub fn needs_displacement(&self) -> bool {
+        self.found.is_none() && self.occupants.iter().all(Option::is_some)
+    }
+}
+
+/// Slot-by-slot view of a table, created by [`CuckooHashTable::dump_slots`].
+pub struct SlotDump<'a, T, S = Slots<T>> {
+    buckets: &'a [S; 2],
+    marker: PhantomData<&'a T>,
+}
+
+impl<T: fmt::Debug, S: BucketStorage<T>> fmt::Debug for SlotDump<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Group<'a, T, S>(&'a S, PhantomData<&'a T>);
+        struct Slot<'a, T>(&'a Option<T>);
+
+        impl<T: fmt::Debug, S: BucketStorage<T>> fmt::Debug for Group<'_, T, S> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_list().entries(self.0.iter().map(Slot)).finish()
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for Slot<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.0 {
+                    Some(x) => x.fmt(f),
+                    None => f.write_str("_"),
+                }
+            }
+        }
+
+        f.debug_list().entries(self.buckets.iter().map(|group| Group(group, PhantomData))).finish()
+    }
+}
+
+/// Occupancy of both bucket groups, created by
+/// [`CuckooHashTable::occupancy_report`]. `Display` renders one line per
+/// group.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OccupancyReport {
+    pub groups: [GroupOccupancy; 2],
+}
+
+/// Occupancy of a single bucket group.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GroupOccupancy {
+    pub buckets: usize,
+    pub occupied: usize,
+    /// Number of maximal runs of consecutive occupied slots.
+    pub filled_runs: usize,
+    pub longest_filled_run: usize,
+    pub longest_empty_run: usize,
+}
+
+impl GroupOccupancy {
+    fn measure<T, S: BucketStorage<T>>(slots: &S) -> Self {
+        let mut report = GroupOccupancy { buckets: slots.len(), ..Default::default() };
+        let mut filled = 0;
+        let mut empty = 0;
+        for slot in slots.iter() {
+            if slot.is_some() {
+                if filled == 0 {
+                    report.filled_runs += 1;
+                }
+                report.occupied += 1;
+                filled += 1;
+                empty = 0;
+                report.longest_filled_run = report.longest_filled_run.max(filled);
+            } else {
+                empty += 1;
+                filled = 0;
+                report.longest_empty_run = report.longest_empty_run.max(empty);
+            }
+        }
+        report
+    }
+
+    /// Fraction of this group's slots that are occupied.
+    pub fn load(&self) -> f64 {
+        self.occupied as f64 / self.buckets as f64
+    }
+
+    /// Average length of a run of occupied slots. Uniform placement at
+    /// load `p` gives about `1 / (1 - p)`; much larger values indicate
+    /// clustering.
+    pub fn mean_filled_run(&self) -> f64 {
+        if self.filled_runs == 0 {
+            0.0
+        } else {
+            self.occupied as f64 / self.filled_runs as f64
+        }
+    }
+}
+
+impl fmt::Display for OccupancyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            writeln!(
+                f,
+                "group {i}: {}/{} occupied ({:.1}%), {} filled runs, mean run {:.2} (uniform {:.2}), longest filled {}, longest empty {}",
+                group.occupied,
+                group.buckets,
+                100.0 * group.load(),
+                group.filled_runs,
+                group.mean_filled_run(),
+                1.0 / (1.0 - group.load()),
+                group.longest_filled_run,
+                group.longest_empty_run,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+type SlotIter<'a, T, S> = Chain<<S as BucketStorage<T>>::Iter<'a>, <S as BucketStorage<T>>::Iter<'a>>;
+
+/// Iterator over the elements of a `CuckooHashTable`, created by
+/// [`CuckooHashTable::iter`].
+pub struct Iter<'a, T: 'a, S: BucketStorage<T> + 'a = Slots<T>> {
+    inner: Flatten<SlotIter<'a, T, S>>,
+    remaining: usize,
+}
+
+impl<'a, T: 'a, S: BucketStorage<T> + 'a> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let next = self.inner.next();
+        if next.is_some() {
+            self.remaining -= 1;
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, S: BucketStorage<T>> ExactSizeIterator for Iter<'_, T, S> {}
+
+impl<'a, T: Hash + Clone + Eq, S: BucketStorage<T>> IntoIterator for &'a CuckooHashTable<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+
+    fn into_iter(self) -> Iter<'a, T, S> {
+        self.iter()
+    }
+}
+
+type OwnedSlotIter<T, S> = Chain<<S as BucketStorage<T>>::IntoIter, <S as BucketStorage<T>>::IntoIter>;
+
+/// Owning iterator over the elements of a `CuckooHashTable`.
+pub struct IntoIter<T, S: BucketStorage<T> = Slots<T>> {
+    inner: OwnedSlotIter<T, S>,
+    remaining: usize,
+}
+
+impl<T, S: BucketStorage<T>> Iterator for IntoIter<T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let next = self.inner.by_ref().flatten().next();
+        if next.is_some() {
+            self.remaining -= 1;
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, S: BucketStorage<T>> ExactSizeIterator for IntoIter<T, S> {}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> IntoIterator for CuckooHashTable<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T, S>;
+
+    fn into_iter(self) -> IntoIter<T, S> {
+        let [first, second] = self.buckets;
+        IntoIter {
+            inner: first.into_slots().chain(second.into_slots()),
+            remaining: self.size,
+        }
+    }
+}
+
+/// Reserves room for the iterator's lower size bound first, or for half
+/// of it if the table is not empty, since some of the elements may
+/// already be present.
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> Extend<T> for CuckooHashTable<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.reserve(if self.is_empty() { lower } else { lower.div_ceil(2) });
+        for x in iter {
+            self.insert(x);
+        }
+    }
+}
+
+impl<'a, T: Hash + Copy + Eq + 'a, S: BucketStorage<T>> Extend<&'a T> for CuckooHashTable<T, S> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> FromIterator<T> for CuckooHashTable<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut table = Self::with_storage(INIT_CAPACITY);
+        table.extend(iter);
+        table
+    }
+}
+
+impl<'a, T: Hash + Copy + Eq + 'a, S: BucketStorage<T>> FromIterator<&'a T> for CuckooHashTable<T, S> {
+    fn from_iter<I: IntoIterator<Item = &'a T>>(iter: I) -> Self {
+        iter.into_iter().copied().collect()
+    }
 }
 
 #[cfg(test)]
@@ -145,7 +1304,8 @@ mod tests {
     use quickcheck::TestResult;
     use quickcheck_macros::quickcheck;
 
-    use crate::cuckoo::CuckooHashTable;
+    use crate::cuckoo::{BoxedSlots, CuckooHashTable, GrowthFactor, Steps, TableStats, INIT_CAPACITY};
+    use crate::error::CuckooError;
 
     #[test]
     fn test_insert_and_contains() {
@@ -171,6 +1331,545 @@ mod tests {
         assert!(!table.remove(&3));
     }
 
+    #[test]
+    fn test_contains_or_insert() {
+        let mut table = CuckooHashTable::new();
+        assert!(!table.contains_or_insert(1));
+        assert!(table.contains_or_insert(1));
+        assert!(table.contains(&1));
+        for x in 2..1000 {
+            assert!(!table.contains_or_insert(x));
+        }
+        for x in 1..1000 {
+            assert!(table.contains_or_insert(x));
+        }
+    }
+
+    #[test]
+    fn test_with_seeds_is_deterministic() {
+        let mut a = CuckooHashTable::with_seeds(1, 2);
+        let mut b = CuckooHashTable::with_seeds(1, 2);
+        for x in 0..100 {
+            a.insert(x);
+            b.insert(x);
+        }
+        for x in 0..100 {
+            assert!(a.contains(&x));
+            assert_eq!(a.h1(&x), b.h1(&x));
+            assert_eq!(a.h2(&x), b.h2(&x));
+        }
+    }
+
+    #[test]
+    fn test_len_and_iter() {
+        let mut table = CuckooHashTable::with_capacity(4);
+        assert!(table.is_empty());
+        for x in 0..100 {
+            table.insert(x);
+        }
+        table.remove(&7);
+        assert_eq!(table.len(), 99);
+        assert_eq!(table.iter().len(), 99);
+        let mut items: Vec<i32> = table.iter().copied().collect();
+        items.sort();
+        assert_eq!(items, (0..100).filter(|&x| x != 7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stats_track_resizes() {
+        let mut table = CuckooHashTable::with_capacity(1);
+        assert_eq!(table.stats(), TableStats::default());
+        for x in 0..100 {
+            table.insert(x);
+        }
+        assert!(table.capacity() > 1);
+        assert_eq!(table.stats().resizes, table.capacity().trailing_zeros() as u64);
+    }
+
+    #[test]
+    fn test_occupancy_report() {
+        let mut table = CuckooHashTable::with_capacity(64);
+        assert_eq!(table.occupancy_report().groups[0].filled_runs, 0);
+        for x in 0..40 {
+            table.insert(x);
+        }
+        let report = table.occupancy_report();
+        let groups = report.groups;
+        assert_eq!(groups[0].occupied + groups[1].occupied, table.len());
+        for group in groups {
+            assert_eq!(group.buckets, table.capacity());
+            assert!(group.longest_filled_run <= group.occupied);
+            assert!(group.filled_runs <= group.occupied);
+            assert!(group.longest_empty_run <= group.buckets - group.occupied);
+        }
+        assert_eq!(report.to_string().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_dump_slots() {
+        let mut table = CuckooHashTable::with_capacity(2);
+        table.insert(5);
+        let dump = format!("{:?}", table.dump_slots());
+        assert!(dump == "[[5, _], [_, _]]" || dump == "[[_, 5], [_, _]]", "{dump}");
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut table = CuckooHashTable::with_capacity(1);
+        let missing = table.explain(&1);
+        assert_eq!(missing.candidates, [0, 0]);
+        assert_eq!(missing.occupants, [None, None]);
+        assert_eq!(missing.found_in(), None);
+        assert!(!missing.needs_displacement());
+
+        table.insert(1);
+        table.insert(2);
+        let present = table.explain(&1);
+        assert!(present.found_in().is_some());
+        assert_eq!(present.occupants[present.found_in().unwrap()], Some(&1));
+        if table.capacity() == 1 {
+            assert!(table.explain(&3).needs_displacement());
+        }
+    }
+
+    #[test]
+    fn test_remove_many() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..1000 {
+            table.insert(x);
+        }
+        assert_eq!(table.remove_many((500..1500).collect::<Vec<_>>()), 500);
+        assert_eq!(table.remove_many([0, 0, 1].iter()), 2);
+        assert_eq!(table.len(), 498);
+        assert!(table.iter().all(|&x| (2..500).contains(&x)));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..100 {
+            table.insert(x);
+        }
+        table.retain(|x| x % 3 == 0);
+        assert_eq!(table.len(), 34);
+        assert!(table.iter().all(|x| x % 3 == 0));
+    }
+
+    #[quickcheck]
+    fn prop_in_place_set_operations_match_std(xs: Vec<u8>, ys: Vec<u8>) -> TestResult {
+        let (mut a, mut b) = (CuckooHashTable::new(), CuckooHashTable::new());
+        for &x in &xs {
+            a.insert(x);
+        }
+        for &y in &ys {
+            b.insert(y);
+        }
+        let set_a: HashSet<u8> = xs.into_iter().collect();
+        let set_b: HashSet<u8> = ys.into_iter().collect();
+
+        let mut intersection = a.clone();
+        intersection.intersect_with(&b);
+        let mut difference = a.clone();
+        difference.subtract(&b);
+
+        let collect = |t: &CuckooHashTable<u8>| t.iter().copied().collect::<HashSet<u8>>();
+        assert_eq!(collect(&intersection), &set_a & &set_b);
+        assert_eq!(intersection.len(), (&set_a & &set_b).len());
+        assert_eq!(collect(&difference), &set_a - &set_b);
+        assert_eq!(difference.len(), (&set_a - &set_b).len());
+        TestResult::passed()
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..200 {
+            table.insert(x);
+        }
+        let mut odd = table.split_off(|x| x % 2 == 1);
+        assert_eq!(table.len(), 100);
+        assert_eq!(odd.len(), 100);
+        for x in 0..200 {
+            assert_eq!(table.contains(&x), x % 2 == 0);
+            assert_eq!(odd.contains(&x), x % 2 == 1);
+        }
+        assert!(odd.insert(1000));
+        assert!(odd.remove(&1));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut table: CuckooHashTable<u32> = CuckooHashTable::new();
+        table.reserve(1000);
+        assert_eq!(table.capacity(), 1024);
+        assert_eq!(table.stats().resizes, 1);
+        table.reserve(10);
+        assert_eq!(table.capacity(), 1024);
+    }
+
+    #[test]
+    fn test_absorb() {
+        let mut a = CuckooHashTable::new();
+        let mut b = CuckooHashTable::new();
+        for x in 0..300 {
+            a.insert(x);
+            b.insert(x + 200);
+        }
+        a.absorb(b);
+        assert_eq!(a.len(), 500);
+        assert!((0..500).all(|x| a.contains(&x)));
+        let mut owned: Vec<i32> = a.into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, (0..500).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_writes() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..5000 {
+            table.insert(x);
+        }
+        let snapshot = table.snapshot();
+        for x in 0..2500 {
+            table.remove(&x);
+        }
+        for x in 5000..6000 {
+            table.insert(x);
+        }
+        let reader = std::thread::spawn(move || {
+            assert_eq!(snapshot.len(), 5000);
+            assert!((0..5000).all(|x| snapshot.contains(&x)));
+            assert!(!snapshot.contains(&5000));
+            snapshot.iter().count()
+        });
+        assert_eq!(reader.join().unwrap(), 5000);
+        assert_eq!(table.len(), 3500);
+        assert!((2500..6000).all(|x| table.contains(&x)));
+    }
+
+    #[test]
+    fn test_cursor_mut() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..1000 {
+            table.insert(x);
+        }
+        let mut cursor = table.cursor_mut();
+        let mut visited = 0;
+        while let Some(&x) = cursor.move_next() {
+            visited += 1;
+            assert_eq!(cursor.current(), Some(&x));
+            if x % 2 == 0 {
+                assert_eq!(cursor.remove_current(), Some(x));
+                assert_eq!(cursor.current(), None);
+            } else if x % 3 == 0 {
+                assert_eq!(cursor.replace_current(x + 10_000), Some(x));
+            } else if x == 1 {
+                // 5 is already in the table, so this only removes 1.
+                assert_eq!(cursor.replace_current(5), Some(1));
+            }
+        }
+        assert_eq!(visited, 1000);
+        assert_eq!(cursor.move_next(), None);
+        drop(cursor);
+        for x in 0..1000 {
+            let expected = x % 2 == 1 && x % 3 != 0 && x != 1;
+            assert_eq!(table.contains(&x), expected, "{x}");
+            assert_eq!(table.contains(&(x + 10_000)), x % 2 == 1 && x % 3 == 0, "{x}");
+        }
+        assert_eq!(table.len(), table.iter().count());
+    }
+
+    #[test]
+    fn test_rehash_with_seeds() {
+        let mut table = CuckooHashTable::with_seeds(1, 2);
+        for x in 0..10 {
+            table.insert(x);
+        }
+        let capacity = table.capacity();
+        let reference = CuckooHashTable::<i32>::with_seeds(3, 4);
+        assert_eq!(reference.capacity(), capacity);
+        table.rehash_with_seeds(3, 4);
+        assert_eq!(table.capacity(), capacity);
+        assert_eq!(table.len(), 10);
+        for x in 0..10 {
+            assert!(table.contains(&x));
+            assert_eq!(table.locations(&x), reference.locations(&x));
+        }
+        table.rehash();
+        assert!((0..10).all(|x| table.contains(&x)));
+        assert_eq!(table.stats().resizes, 0);
+    }
+
+    #[test]
+    fn test_identity_hashing() {
+        let mut table = CuckooHashTable::with_identity_hashing(64);
+        let keys: Vec<u64> = (0..40u64).map(|x| x.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+        for &key in &keys {
+            assert!(table.insert(key));
+        }
+        for &key in &keys {
+            assert!(table.contains(&key));
+            let (b0, b1) = table.locations(&key);
+            assert_eq!(b0, (key & 0xffff_ffff) as usize % table.capacity());
+            assert_eq!(b1, (key >> 32) as usize % table.capacity());
+        }
+        table.rehash();
+        assert!(keys.iter().all(|key| table.contains(key)));
+        table.rehash_with_seeds(1, 2);
+        assert!(keys.iter().all(|key| table.contains(key)));
+    }
+
+    #[test]
+    fn test_wide_hashing() {
+        let mut table = CuckooHashTable::with_wide_hashing(16);
+        for x in 0..1000 {
+            assert!(table.insert(x));
+        }
+        assert_eq!(table.len(), 1000);
+        assert!((0..1000).all(|x| table.contains(&x)));
+        for x in 0..1000 {
+            let (b0, b1) = table.locations(&x);
+            assert_eq!((b0, b1), (table.h1(&x), table.h2(&x)));
+        }
+        assert_eq!(table.remove_many(0..500), 500);
+        table.rehash();
+        assert!((500..1000).all(|x| table.contains(&x)));
+
+        let seeded = CuckooHashTable::<i32>::with_wide_seed(5);
+        let same = CuckooHashTable::<i32>::with_wide_seed(5);
+        assert!((0..100).all(|x| seeded.locations(&x) == same.locations(&x)));
+    }
+
+    #[test]
+    fn test_locations_match_placement() {
+        let mut table = CuckooHashTable::with_seeds(1, 2);
+        for x in 0..20 {
+            table.insert(x);
+        }
+        let other = CuckooHashTable::<i32>::with_seeds(1, 2);
+        for x in 0..20 {
+            let (b0, b1) = table.locations(&x);
+            assert!(b0 < table.capacity() && b1 < table.capacity());
+            assert_eq!(table.explain(&x).candidates, [b0, b1]);
+            assert!(table.explain(&x).found_in().is_some());
+            if table.capacity() == other.capacity() {
+                assert_eq!(other.locations(&x), (b0, b1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fallible_capacity() {
+        assert_eq!(
+            CuckooHashTable::<u64>::try_with_capacity(usize::MAX).unwrap_err(),
+            CuckooError::CapacityOverflow
+        );
+        // Each bucket holds an `Option<[u64; 1]>` of 16 bytes in both groups.
+        let capacity = isize::MAX as usize / 32 + 1;
+        assert_eq!(
+            CuckooHashTable::<[u64; 1]>::try_with_capacity(capacity).unwrap_err(),
+            CuckooError::AllocationFailed { bytes: capacity * 32 }
+        );
+
+        let mut table = CuckooHashTable::new();
+        assert_eq!(table.try_insert(1u64), Ok(true));
+        assert_eq!(table.try_insert(1), Ok(false));
+        assert_eq!(table.try_reserve(usize::MAX), Err(CuckooError::CapacityOverflow));
+        assert_eq!(table.capacity(), 16);
+        assert_eq!(table.try_reserve(100), Ok(()));
+        assert!(table.capacity() >= 100);
+        assert!(table.contains(&1));
+        for x in 2..1000 {
+            assert_eq!(table.try_insert(x), Ok(true));
+        }
+        assert!((1..1000).all(|x| table.contains(&x)));
+    }
+
+    #[test]
+    fn test_fallible_allocation() {
+        // Representable, but far more than any address space holds.
+        let mut table = CuckooHashTable::<u64, BoxedSlots<u64>>::with_storage(16);
+        table.insert(1);
+        assert!(matches!(table.try_reserve(1 << 50), Err(CuckooError::AllocationFailed { .. })));
+        assert_eq!(table.capacity(), 16);
+        assert!(table.contains(&1));
+    }
+
+    #[test]
+    fn test_try_insert_bounded_undoes_failed_chains() {
+        let mut table = CuckooHashTable::with_seeds(1, 2);
+        let mut placed = Vec::new();
+        let mut failures = 0;
+        for x in 0..64u64 {
+            match table.try_insert_bounded(x, 8) {
+                Ok(inserted) => {
+                    assert!(inserted);
+                    placed.push(x);
+                }
+                Err(CuckooError::InsertionFailed { displacements }) => {
+                    assert_eq!(displacements, 8);
+                    failures += 1;
+                }
+                Err(err) => panic!("unexpected error: {err}"),
+            }
+            assert_eq!(table.len(), placed.len());
+            assert!(placed.iter().all(|x| table.contains(x)));
+        }
+        assert!(failures > 0);
+        assert_eq!(table.capacity(), INIT_CAPACITY);
+        assert_eq!(table.try_insert_bounded(placed[0], 8), Ok(false));
+    }
+
+    #[test]
+    fn test_insert_or_evict_keeps_capacity() {
+        let mut table = CuckooHashTable::with_capacity(16);
+        let mut evicted = Vec::new();
+        for x in 0..100 {
+            evicted.extend(table.insert_or_evict(x));
+        }
+        assert_eq!(table.capacity(), 16);
+        assert_eq!(table.stats().resizes, 0);
+        assert_eq!(table.stats().evictions, evicted.len() as u64);
+        assert_eq!(table.len() + evicted.len(), 100);
+        assert!(evicted.iter().all(|x| !table.contains(x)));
+        assert!(table.iter().all(|x| !evicted.contains(x)));
+        let present = *table.iter().next().unwrap();
+        assert_eq!(table.insert_or_evict(present), None);
+        assert_eq!(table.len() + evicted.len(), 100);
+    }
+
+    #[test]
+    fn test_extend_and_collect() {
+        let numbers = [3u32, 1, 4, 1, 5, 9, 2, 6];
+        let mut table: CuckooHashTable<u32> = numbers.iter().collect();
+        assert_eq!(table.len(), 7);
+        table.extend(numbers.iter().map(|x| x * 10));
+        table.extend(&[0, 1, 2]);
+        assert_eq!(table.len(), 15);
+        assert!(numbers.iter().all(|x| table.contains(x) && table.contains(&(x * 10))));
+
+        let words: CuckooHashTable<String> = ["a", "b", "a"].into_iter().map(String::from).collect();
+        assert_eq!(words.len(), 2);
+    }
+
+    #[test]
+    fn test_partition_into() {
+        let table: CuckooHashTable<u32> = (0..1000).collect();
+        let shards = table.partition_into(4, |&x| x as usize);
+        assert_eq!(shards.len(), 4);
+        for (index, shard) in shards.iter().enumerate() {
+            assert_eq!(shard.len(), 250);
+            assert!(shard.iter().all(|&x| x as usize % 4 == index));
+        }
+
+        // Shards are separate tables, so a handle from the source table
+        // or from one shard is valid in none of the others.
+        let mut table: CuckooHashTable<u32> = (0..100).collect();
+        let (_, handle) = table.insert_with_handle(100);
+        let mut shards = table.partition_into(2, |&x| x as usize);
+        let (_, shard_handle) = shards[0].insert_with_handle(200);
+        for shard in &shards {
+            assert_eq!(shard.try_get_by_handle(handle), Err(CuckooError::StaleGeneration));
+        }
+        assert_eq!(shards[1].try_get_by_handle(shard_handle), Err(CuckooError::StaleGeneration));
+        assert_eq!(shards[0].get_by_handle(shard_handle), Some(&200));
+    }
+
+    /// The kick chain used to evict only from the first bucket group, so
+    /// an evicted element found its replacement in its other slot and the
+    /// two swapped places until the chain gave up. Large tables resized
+    /// at about 11% load. With both groups evicting, a table 40% full
+    /// should place every element without running out of kicks.
+    #[test]
+    fn test_kick_chain_does_not_livelock() {
+        let mut table = CuckooHashTable::with_seeds(1, 2);
+        table.reserve(1 << 14);
+        let capacity = table.capacity();
+        let target = (0.4 * (2 * capacity) as f64) as u32;
+        for x in 0..target {
+            assert_eq!(table.insert_or_evict(x), None, "ran out of kicks at {x} of {target}");
+        }
+        assert_eq!(table.capacity(), capacity);
+        assert!(table.stats().displacements > 0);
+    }
+
+    #[test]
+    fn test_retain_copies_only_changed_chunks() {
+        let mut table: CuckooHashTable<u32> = (0..5000).collect();
+        let snapshot = table.snapshot();
+        let chunks = table.buckets[0].shared_chunks() + table.buckets[1].shared_chunks();
+        assert!(chunks > 2);
+        table.retain(|_| true);
+        assert_eq!(table.buckets[0].shared_chunks() + table.buckets[1].shared_chunks(), chunks);
+        table.retain(|&x| x != 7);
+        assert_eq!(table.buckets[0].shared_chunks() + table.buckets[1].shared_chunks(), chunks - 1);
+        assert!(snapshot.contains(&7) && !table.contains(&7));
+    }
+
+    #[test]
+    fn test_insert_bounded() {
+        let mut table = CuckooHashTable::with_capacity(16);
+        let mut evicted = Vec::new();
+        for x in 0..40 {
+            evicted.extend(table.insert_bounded(x, 0));
+        }
+        assert_eq!(table.stats().displacements, 0);
+        assert_eq!(table.len() + evicted.len(), 40);
+
+        let mut table = CuckooHashTable::with_capacity(16);
+        for x in 0..100 {
+            let before = table.stats().displacements;
+            let mut displaced = Vec::new();
+            let victim = table.insert_bounded_tracked(x, 3, &mut displaced);
+            let kicks = table.stats().displacements - before;
+            assert!(kicks <= 3);
+            assert_eq!(displaced.len() as u64, kicks);
+            if let Some(victim) = victim {
+                assert!(!table.contains(&victim));
+            }
+            for moved in displaced.iter().filter(|&&moved| Some(moved) != victim) {
+                assert!(table.contains(moved));
+            }
+        }
+        assert_eq!(table.capacity(), 16);
+    }
+
+    #[test]
+    fn test_resize_policies() {
+        let mut table = CuckooHashTable::with_seeds(1, 2).with_resize_policy(GrowthFactor::new(1.5));
+        for x in 0..1000 {
+            table.insert(x);
+        }
+        let mut capacity = 16;
+        for _ in 0..table.stats().resizes {
+            capacity = (capacity as f64 * 1.5) as usize;
+        }
+        assert_eq!(table.capacity(), capacity);
+        assert!((0..1000).all(|x| table.contains(&x)));
+
+        let mut table = CuckooHashTable::with_seeds(1, 2).with_resize_policy(Steps::new(vec![100, 40]));
+        for x in 0..30 {
+            table.insert(x);
+        }
+        assert_eq!(table.capacity(), 40);
+        table.reserve(60);
+        assert_eq!(table.capacity(), 100);
+        table.reserve(100);
+        assert_eq!(table.capacity(), 200);
+    }
+
+    #[test]
+    fn test_max_load_grows_ahead_of_failures() {
+        let policy = GrowthFactor::default().with_max_load(0.25);
+        let mut table = CuckooHashTable::with_capacity(64).with_resize_policy(policy);
+        for x in 0..32 {
+            table.insert(x);
+        }
+        assert_eq!(table.capacity(), 64);
+        table.insert(32);
+        assert_eq!(table.capacity(), 128);
+    }
+
     #[quickcheck]
     fn prop_insert_and_delete_are_consistent_with_contains_and_std_hashmap(xs: Vec<i32>) -> TestResult {
         let mut table = CuckooHashTable::new();
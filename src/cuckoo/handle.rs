@@ -0,0 +1,291 @@
+//! Handles that find an element's slot again without hashing its key.
+//!
+//! A handle records the slot an element was placed in together with a
+//! version of that slot. The version is bumped whenever an element leaves
+//! the slot, and rebuilding the table moves it to a new generation, so a
+//! handle to an element that moved or was removed is detected instead of
+//! silently referring to whatever took its place. The `try_*` lookups say
+//! which of the two happened. Generations come from one process-wide
+//! counter, so no two tables ever share one and a handle used with the
+//! wrong table is caught the same way.
+//!
+//! Iterators and cursors borrow the table, so the compiler already rules
+//! out modifying the table underneath them. Handles are the only
+//! references to slots that outlive a borrow.
+
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{BucketStorage, CuckooHashTable};
+use crate::error::CuckooError;
+
+/// Position of an element in a [`CuckooHashTable`], returned by
+/// [`CuckooHashTable::insert_with_handle`].
+///
+/// A handle stays valid until its element is removed or moved to another
+/// slot, either by a displacement chain or by a resize or rehash. Any
+/// other table, including a clone of the one that issued it, reports it
+/// as [`CuckooError::StaleGeneration`].
+///
+/// A handle is one word, a 64-bit generation and a 32-bit version: 16
+/// bytes on 32-bit targets such as wasm32 and 24 on 64-bit ones. The
+/// generation is not narrowed, because one that wrapped around would let
+/// a handle from long ago validate again. Versions do not wrap either: a
+/// slot vacated `u32::MAX` times is retired, and handles to it fail with
+/// [`CuckooError::StaleSlot`] until a resize or rehash starts its
+/// versions over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SlotHandle {
+    /// The slot's index within its bucket group, shifted left by one,
+    /// with the group in the low bit. Bucket storage never takes more
+    /// than `isize::MAX` bytes, so the shift cannot overflow.
+    slot: usize,
+    epoch: u64,
+    version: u32,
+}
+
+impl SlotHandle {
+    #[inline]
+    fn group(self) -> usize {
+        self.slot & 1
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.slot >> 1
+    }
+}
+
+static NEXT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a generation that no table in the process has had before.
+pub(super) fn next_epoch() -> u64 {
+    NEXT_EPOCH.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Version of a retired slot, which no handle matches.
+const RETIRED: u32 = u32::MAX;
+
+/// Per-slot versions of both bucket groups. Nothing is allocated until
+/// the first handle is issued, so tables that never use handles pay only
+/// for a `None` check when a slot is vacated.
+#[derive(Clone, Default)]
+pub(super) struct SlotVersions(Option<Box<[Vec<u32>; 2]>>);
+
+impl SlotVersions {
+    /// Returns fresh versions for a table with `capacity` buckets per
+    /// group, tracked only if these are.
+    pub(super) fn like(&self, capacity: usize) -> Self {
+        match self.0 {
+            Some(_) => SlotVersions(Some(Box::new([vec![0; capacity], vec![0; capacity]]))),
+            None => SlotVersions(None),
+        }
+    }
+
+    fn track(&mut self, capacity: usize) {
+        self.0.get_or_insert_with(|| Box::new([vec![0; capacity], vec![0; capacity]]));
+    }
+
+    /// Invalidates handles to whatever occupies the given slot. A slot
+    /// whose version runs out is retired rather than wrapped, which would
+    /// bring its oldest handles back to life.
+    #[inline]
+    pub(super) fn bump(&mut self, group: usize, index: usize) {
+        if let Some(versions) = &mut self.0 {
+            versions[group][index] = versions[group][index].saturating_add(1);
+        }
+    }
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Inserts `x` if it is not present and returns whether it was
+    /// inserted, together with a handle to its slot. Calling this for an
+    /// element that is already present just returns its handle.
+    ///
+    /// The first call turns on per-slot version tracking for this table,
+    /// which costs four bytes per slot.
+    pub fn insert_with_handle(&mut self, x: T) -> (bool, SlotHandle) {
+        self.versions.track(self.capacity);
+        let (b0, b1) = self.indices(&x);
+        let slot0 = self.buckets[0][b0].as_ref();
+        let slot1 = self.buckets[1][b1].as_ref();
+        if slot0 == Some(&x) {
+            return (false, self.handle_at(0, b0));
+        }
+        if slot1 == Some(&x) {
+            return (false, self.handle_at(1, b1));
+        }
+        let (free0, free1) = (slot0.is_none(), slot1.is_none());
+        if !self.exceeds_max_load(self.size + 1) {
+            if free0 {
+                self.insert_into_slot(0, b0, x);
+                return (true, self.handle_at(0, b0));
+            }
+            if free1 {
+                self.insert_into_slot(1, b1, x);
+                return (true, self.handle_at(1, b1));
+            }
+        }
+        // Displacement or a resize may move `x` after placing it, so look
+        // it up again once it has settled.
+        self.insert(x.clone());
+        let b0 = self.h1(&x);
+        let handle = if self.buckets[0][b0].as_ref() == Some(&x) {
+            self.handle_at(0, b0)
+        } else {
+            self.handle_at(1, self.h2(&x))
+        };
+        (true, handle)
+    }
+
+    /// Returns the element `handle` refers to, or `None` if it has been
+    /// removed or moved since the handle was issued.
+    pub fn get_by_handle(&self, handle: SlotHandle) -> Option<&T> {
+        self.try_get_by_handle(handle).ok()
+    }
+
+    /// Like `get_by_handle`, but says why the handle no longer refers to
+    /// an element.
+    pub fn try_get_by_handle(&self, handle: SlotHandle) -> Result<&T, CuckooError> {
+        self.check_handle(handle)?;
+        self.buckets[handle.group()][handle.index()].as_ref().ok_or(CuckooError::StaleSlot)
+    }
+
+    /// Removes and returns the element `handle` refers to without hashing
+    /// it. Returns `None`, leaving the table unchanged, if the element has
+    /// been removed or moved since the handle was issued.
+    pub fn remove_by_handle(&mut self, handle: SlotHandle) -> Option<T> {
+        self.try_remove_by_handle(handle).ok()
+    }
+
+    /// Like `remove_by_handle`, but says why the handle no longer refers
+    /// to an element.
+    pub fn try_remove_by_handle(&mut self, handle: SlotHandle) -> Result<T, CuckooError> {
+        self.check_handle(handle)?;
+        let removed = self.buckets[handle.group()][handle.index()].take().ok_or(CuckooError::StaleSlot)?;
+        self.versions.bump(handle.group(), handle.index());
+        self.size -= 1;
+        Ok(removed)
+    }
+
+    /// Returns the table's generation, which changes whenever the table
+    /// is resized, rehashed or emptied in bulk and so every element may
+    /// have moved. No two tables share a generation. Bucket indices from `locations` or `explain` are only
+    /// meaningful while the generation they were read in is current.
+    pub fn generation(&self) -> u64 {
+        self.epoch
+    }
+
+    fn handle_at(&self, group: usize, index: usize) -> SlotHandle {
+        let versions = self.versions.0.as_ref().expect("slot versions are tracked");
+        SlotHandle { slot: index << 1 | group, epoch: self.epoch, version: versions[group][index] }
+    }
+
+    fn check_handle(&self, handle: SlotHandle) -> Result<(), CuckooError> {
+        if handle.epoch != self.epoch {
+            return Err(CuckooError::StaleGeneration);
+        }
+        let current = self
+            .versions
+            .0
+            .as_ref()
+            .and_then(|versions| versions[handle.group()].get(handle.index()))
+            .is_some_and(|&version| version == handle.version && version != RETIRED);
+        if current {
+            Ok(())
+        } else {
+            Err(CuckooError::StaleSlot)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use crate::cuckoo::{CuckooHashTable, SlotHandle};
+    use crate::error::CuckooError;
+
+    #[test]
+    fn test_remove_by_handle() {
+        let mut table = CuckooHashTable::with_capacity(1024);
+        let handles: Vec<_> = (0..100).map(|x| table.insert_with_handle(x)).collect();
+        assert!(handles.iter().all(|&(inserted, _)| inserted));
+        assert_eq!(table.insert_with_handle(5), (false, handles[5].1));
+        for (x, &(_, handle)) in handles.iter().enumerate() {
+            assert_eq!(table.get_by_handle(handle), Some(&(x as i32)));
+        }
+        assert_eq!(table.remove_by_handle(handles[3].1), Some(3));
+        assert_eq!(table.try_remove_by_handle(handles[3].1), Err(CuckooError::StaleSlot));
+        assert_eq!(table.get_by_handle(handles[3].1), None);
+        assert!(!table.contains(&3));
+        assert_eq!(table.len(), 99);
+
+        // Reusing the slot does not revive the old handle.
+        let (_, reused) = table.insert_with_handle(3);
+        assert_eq!(table.get_by_handle(handles[3].1), None);
+        assert!(table.remove(&4));
+        assert_eq!(table.get_by_handle(handles[4].1), None);
+        assert_eq!(table.get_by_handle(reused), Some(&3));
+        let expected = if cfg!(target_pointer_width = "64") { 24 } else { 16 };
+        assert_eq!(mem::size_of::<SlotHandle>(), expected);
+    }
+
+    #[test]
+    fn test_handles_detect_moves() {
+        let mut table = CuckooHashTable::with_capacity(4);
+        let handles: Vec<_> = (0..1000).map(|x| (x, table.insert_with_handle(x).1)).collect();
+        assert!(table.stats().resizes > 0);
+        let mut valid = 0;
+        for (x, handle) in handles {
+            if let Some(&found) = table.get_by_handle(handle) {
+                assert_eq!(found, x);
+                assert_eq!(table.remove_by_handle(handle), Some(x));
+                valid += 1;
+            }
+        }
+        assert!(valid > 0);
+        assert_eq!(table.len(), 1000 - valid);
+
+        table.rehash();
+        let (_, handle) = table.insert_with_handle(5000);
+        let generation = table.generation();
+        table.rehash();
+        assert_ne!(table.generation(), generation);
+        assert_eq!(table.try_get_by_handle(handle), Err(CuckooError::StaleGeneration));
+        assert_eq!(table.try_remove_by_handle(handle), Err(CuckooError::StaleGeneration));
+        assert!(table.contains(&5000));
+    }
+
+    #[test]
+    fn test_retired_slots_issue_no_valid_handles() {
+        let mut table = CuckooHashTable::with_capacity(64);
+        let (_, handle) = table.insert_with_handle(1);
+        table.versions.0.as_mut().unwrap()[handle.group()][handle.index()] = u32::MAX - 2;
+        assert!(table.remove(&1));
+        let (_, reused) = table.insert_with_handle(1);
+        assert_eq!(reused.slot, handle.slot);
+        assert_eq!(table.get_by_handle(reused), Some(&1));
+        assert!(table.remove(&1));
+        let (_, retired) = table.insert_with_handle(1);
+        assert_eq!(retired.slot, handle.slot);
+        assert_eq!(table.try_get_by_handle(retired), Err(CuckooError::StaleSlot));
+
+        table.rehash();
+        let (_, fresh) = table.insert_with_handle(1);
+        assert_eq!(table.get_by_handle(fresh), Some(&1));
+    }
+
+    #[test]
+    fn test_handles_are_rejected_by_other_tables() {
+        let mut table = CuckooHashTable::with_capacity(64);
+        let mut other = CuckooHashTable::with_capacity(64);
+        let (_, handle) = table.insert_with_handle(1);
+        other.insert_with_handle(1);
+        let clone = table.clone();
+        assert_eq!(other.try_get_by_handle(handle), Err(CuckooError::StaleGeneration));
+        assert_eq!(clone.try_get_by_handle(handle), Err(CuckooError::StaleGeneration));
+        assert_ne!(clone.generation(), table.generation());
+        assert_eq!(table.get_by_handle(handle), Some(&1));
+    }
+}
@@ -0,0 +1,151 @@
+//! Hash functions for the two bucket groups of a table.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use siphasher::sip128::{Hasher128, SipHasher13};
+
+/// The pair of hash functions a table places its elements with.
+#[derive(Clone)]
+pub(super) enum Hashers {
+    /// Two independently seeded instances of the standard hasher.
+    Seeded(DefaultHasher, DefaultHasher),
+    /// Keys are hashed with [`IdentityHasher`], and the low and high 32
+    /// bits of the result serve as the two hashes.
+    Identity,
+    /// Keys are hashed once with keyed SipHash-1-3 with 128-bit output,
+    /// and its two 64-bit halves serve as the two hashes.
+    Wide(SipHasher13),
+}
+
+impl Hashers {
+    /// Wide hashing keyed with the 128-bit key `key0`, `key1`.
+    pub(super) fn wide(key0: u64, key1: u64) -> Self {
+        Hashers::Wide(SipHasher13::new_with_keys(key0, key1))
+    }
+
+    /// Hashes `x` for both bucket groups at once. Only the wide mode
+    /// actually saves work over two calls to `hash`.
+    #[inline]
+    pub(super) fn hash_pair<T: Hash + ?Sized>(&self, x: &T) -> (u64, u64) {
+        match self {
+            Hashers::Wide(keyed) => {
+                let mut hasher = *keyed;
+                x.hash(&mut hasher);
+                let hash = hasher.finish128();
+                (hash.h1, hash.h2)
+            }
+            _ => (self.hash(0, x), self.hash(1, x)),
+        }
+    }
+
+    /// Hashes `x` for bucket group `group`.
+    #[inline]
+    pub(super) fn hash<T: Hash + ?Sized>(&self, group: usize, x: &T) -> u64 {
+        match self {
+            Hashers::Seeded(h1, h2) => {
+                let mut hasher = if group == 0 { h1.clone() } else { h2.clone() };
+                x.hash(&mut hasher);
+                hasher.finish()
+            }
+            Hashers::Identity => {
+                let mut hasher = IdentityHasher::default();
+                x.hash(&mut hasher);
+                let hash = hasher.finish();
+                if group == 0 { hash & 0xffff_ffff } else { hash >> 32 }
+            }
+            Hashers::Wide(_) => {
+                let (low, high) = self.hash_pair(x);
+                if group == 0 { low } else { high }
+            }
+        }
+    }
+}
+
+/// A hasher that passes keys through instead of hashing them, for keys
+/// that are already uniformly random such as UUIDs or truncated digests.
+///
+/// Every value written is folded into the state with xor, so a key that
+/// writes a single integer hashes to itself and a longer key hashes to the
+/// xor of its 64-bit words. Keys that are not uniformly random in every
+/// bit pile into the same buckets and make the table resize over and
+/// over.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdentityHasher {
+    hash: u64,
+}
+
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.hash ^= u64::from_le_bytes(word);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.hash ^= u64::from(i);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.hash ^= u64::from(i);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.hash ^= u64::from(i);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.hash ^= i;
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.hash ^= i as u64 ^ (i >> 64) as u64;
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.hash ^= i as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{Hash, Hasher};
+
+    use siphasher::sip128::{Hasher128, SipHasher13};
+
+    use crate::cuckoo::hashing::{Hashers, IdentityHasher};
+
+    fn identity<T: Hash>(x: T) -> u64 {
+        let mut hasher = IdentityHasher::default();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_identity_hasher() {
+        assert_eq!(identity(0x0123_4567_89ab_cdefu64), 0x0123_4567_89ab_cdef);
+        assert_eq!(identity(7u32), 7);
+        assert_eq!(identity(1u128 << 64 | 2), 3);
+        assert_eq!(Hashers::Identity.hash(0, &0x0123_4567_89ab_cdefu64), 0x89ab_cdef);
+        assert_eq!(Hashers::Identity.hash(1, &0x0123_4567_89ab_cdefu64), 0x0123_4567);
+    }
+
+    #[test]
+    fn test_wide_hash_pair() {
+        let wide = Hashers::wide(3, 4);
+        let (low, high) = wide.hash_pair("key");
+        let mut hasher = SipHasher13::new_with_keys(3, 4);
+        "key".hash(&mut hasher);
+        let hash = hasher.finish128();
+        assert_eq!((low, high), (hash.h1, hash.h2));
+        assert_ne!(low, high);
+        assert_eq!(wide.hash(0, "key"), low);
+        assert_eq!(wide.hash(1, "key"), high);
+        assert_eq!(Hashers::Identity.hash_pair(&(5u64 << 32 | 9)), (9, 5));
+    }
+}
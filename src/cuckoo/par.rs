@@ -0,0 +1,106 @@
+//! Parallel operations, enabled with the `rayon` feature. Work is split
+//! along the chunks of the bucket storage, which are disjoint.
+
+use std::hash::Hash;
+use std::mem;
+
+use rayon::prelude::*;
+
+use super::slots::CHUNK_SHIFT;
+use super::CuckooHashTable;
+
+impl<T: Hash + Clone + Eq + Send + Sync> CuckooHashTable<T> {
+    /// Returns a parallel iterator over the elements.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = &T> {
+        let [first, second] = &self.buckets;
+        first
+            .par_chunks()
+            .chain(second.par_chunks())
+            .flat_map_iter(|chunk| chunk.iter().flatten())
+    }
+
+    /// Keeps only the elements for which `f` returns `true`, evaluating
+    /// `f` on all threads of the rayon pool. As with `retain`, handles to
+    /// the elements that are kept stay valid.
+    pub fn par_retain<F: Fn(&T) -> bool + Sync>(&mut self, f: F) {
+        let mut removed = 0;
+        for (group, bucket) in self.buckets.iter_mut().enumerate() {
+            // Find the doomed slots through a shared borrow, so chunks
+            // shared with a snapshot are only copied if they change.
+            let doomed: Vec<Vec<usize>> = bucket
+                .par_chunks()
+                .map(|chunk| {
+                    let slots = chunk.iter().enumerate();
+                    slots.filter(|(_, slot)| slot.as_ref().is_some_and(|x| !f(x))).map(|(index, _)| index).collect()
+                })
+                .collect();
+            removed += doomed.iter().map(Vec::len).sum::<usize>();
+            bucket.par_clear(&doomed);
+            for (chunk, offsets) in doomed.iter().enumerate() {
+                for &offset in offsets {
+                    self.versions.bump(group, chunk << CHUNK_SHIFT | offset);
+                }
+            }
+        }
+        self.size -= removed;
+    }
+
+    /// Removes every element and returns them as a parallel iterator. The
+    /// table is left empty with its capacity and hash functions intact.
+    pub fn par_drain(&mut self) -> impl ParallelIterator<Item = T> {
+        let empty = self.empty_like(self.capacity);
+        let [first, second] = mem::replace(&mut self.buckets, empty.buckets);
+        self.size = 0;
+        self.epoch = empty.epoch;
+        first.into_par_iter().chain(second.into_par_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use crate::cuckoo::CuckooHashTable;
+    use crate::error::CuckooError;
+
+    #[test]
+    fn test_par_retain_copies_only_changed_chunks() {
+        let mut table: CuckooHashTable<u32> = (0..5000).collect();
+        let snapshot = table.snapshot();
+        let shared = |table: &CuckooHashTable<u32>| table.buckets[0].shared_chunks() + table.buckets[1].shared_chunks();
+        let chunks = shared(&table);
+        table.par_retain(|_| true);
+        assert_eq!(shared(&table), chunks);
+        table.par_retain(|&x| x != 7);
+        assert_eq!(shared(&table), chunks - 1);
+        assert!(snapshot.contains(&7) && !table.contains(&7));
+        assert_eq!(table.len(), 4999);
+    }
+
+    #[test]
+    fn test_par_retain_and_drain() {
+        let mut table = CuckooHashTable::new();
+        for x in 0..10_000u32 {
+            table.insert(x);
+        }
+        assert_eq!(table.par_iter().map(|&x| u64::from(x)).sum::<u64>(), 49_995_000);
+
+        let (_, kept) = table.insert_with_handle(10_000);
+        let (_, doomed) = table.insert_with_handle(10_001);
+        table.par_retain(|x| x % 10 == 0);
+        assert_eq!(table.len(), 1001);
+        assert!(table.iter().all(|x| x % 10 == 0));
+        assert_eq!(table.get_by_handle(kept), Some(&10_000));
+        assert_eq!(table.try_get_by_handle(doomed), Err(CuckooError::StaleSlot));
+        assert!(table.remove(&10_000));
+
+        let capacity = table.capacity();
+        let mut drained: Vec<u32> = table.par_drain().collect();
+        drained.sort();
+        assert_eq!(drained, (0..10_000).step_by(10).collect::<Vec<_>>());
+        assert!(table.is_empty());
+        assert_eq!(table.iter().count(), 0);
+        assert_eq!(table.capacity(), capacity);
+        assert!(table.insert(7));
+    }
+}
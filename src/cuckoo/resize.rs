@@ -0,0 +1,113 @@
+//! Policies deciding how far a table grows and how full it may get.
+
+/// Consulted whenever a `CuckooHashTable` has to grow.
+pub trait ResizePolicy: Send + Sync {
+    /// Returns the capacity to grow to from `capacity`. Anything not
+    /// larger than `capacity` is treated as `capacity + 1`.
+    fn next_capacity(&self, capacity: usize) -> usize;
+
+    /// Fraction of all slots that may be occupied before the table grows
+    /// ahead of time, in `(0, 1]`. The default of 1.0 means the table only
+    /// grows when an insertion cannot find a place.
+    fn max_load(&self) -> f64 {
+        1.0
+    }
+}
+
+/// Multiplies the capacity by a constant factor. The default doubles the
+/// capacity and only grows when an insertion fails, which is what
+/// `CuckooHashTable::new` uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GrowthFactor {
+    factor: f64,
+    max_load: f64,
+}
+
+impl GrowthFactor {
+    /// # Panics
+    ///
+    /// Panics if `factor` is not a finite number greater than 1, since
+    /// the table would then never grow.
+    pub fn new(factor: f64) -> Self {
+        assert!(factor.is_finite() && factor > 1.0, "growth factor must be finite and greater than 1, got {factor}");
+        GrowthFactor { factor, max_load: 1.0 }
+    }
+
+    /// Also grows as soon as more than `max_load` of the slots would be
+    /// occupied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_load` is not in `(0, 1]`.
+    pub fn with_max_load(self, max_load: f64) -> Self {
+        assert!(max_load > 0.0 && max_load <= 1.0, "maximum load must be in (0, 1], got {max_load}");
+        GrowthFactor { max_load, ..self }
+    }
+
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+}
+
+impl Default for GrowthFactor {
+    fn default() -> Self {
+        GrowthFactor::new(2.0)
+    }
+}
+
+impl ResizePolicy for GrowthFactor {
+    fn next_capacity(&self, capacity: usize) -> usize {
+        (capacity as f64 * self.factor) as usize
+    }
+
+    fn max_load(&self) -> f64 {
+        self.max_load
+    }
+}
+
+/// Grows through a fixed list of capacities, then doubles once the list
+/// is exhausted. Useful to keep peak memory during a rehash predictable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Steps {
+    capacities: Vec<usize>,
+}
+
+impl Steps {
+    pub fn new(mut capacities: Vec<usize>) -> Self {
+        capacities.sort_unstable();
+        Steps { capacities }
+    }
+}
+
+impl ResizePolicy for Steps {
+    fn next_capacity(&self, capacity: usize) -> usize {
+        self.capacities
+            .iter()
+            .copied()
+            .find(|&step| step > capacity)
+            .unwrap_or_else(|| capacity.saturating_mul(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cuckoo::{GrowthFactor, ResizePolicy};
+
+    #[test]
+    fn test_growth_factor_accepts_valid_values() {
+        let policy = GrowthFactor::new(1.5).with_max_load(0.5);
+        assert_eq!((policy.factor(), policy.max_load()), (1.5, 0.5));
+        assert_eq!(GrowthFactor::new(2.0).with_max_load(1.0).next_capacity(16), 32);
+    }
+
+    #[test]
+    fn test_growth_factor_rejects_invalid_values() {
+        for factor in [1.0, 0.5, -2.0, f64::NAN, f64::INFINITY] {
+            assert!(std::panic::catch_unwind(|| GrowthFactor::new(factor)).is_err(), "factor {factor}");
+        }
+        for max_load in [0.0, -0.5, 1.5, f64::NAN, f64::INFINITY] {
+            let result = std::panic::catch_unwind(|| GrowthFactor::default().with_max_load(max_load));
+            assert!(result.is_err(), "max load {max_load}");
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Random sampling, enabled with the `rand` feature.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use super::{BucketStorage, CuckooHashTable};
+
+/// How many slots a sequential pass walks in the time of one random
+/// probe, roughly.
+const PROBE_COST: f64 = 4.0;
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Returns `k` distinct elements chosen uniformly at random, in no
+    /// particular order, or every element if the table holds fewer.
+    ///
+    /// Small samples probe random slots and retry on empty ones, which
+    /// takes about `2 * capacity / len` probes per element and never
+    /// walks the table. When that adds up to more than a fraction of the
+    /// `2 * capacity` slots, because the sample is large or the table is
+    /// sparse, ranks are drawn up front instead and collected in a single
+    /// pass.
+    pub fn sample_k<R: Rng + ?Sized>(&self, rng: &mut R, k: usize) -> Vec<&T> {
+        if k >= self.size {
+            return self.iter().collect();
+        }
+        let groups = [self.buckets[0].len(), self.buckets[1].len()];
+        let slots = (groups[0] + groups[1]) as f64;
+        let probes = k as f64 * slots / self.size as f64;
+        if probes * PROBE_COST <= slots {
+            let mut seen = HashSet::with_capacity(k);
+            let mut sample = Vec::with_capacity(k);
+            while sample.len() < k {
+                let slot = rng.gen_range(0..groups[0] + groups[1]);
+                let (group, bucket) = if slot < groups[0] { (0, slot) } else { (1, slot - groups[0]) };
+                if let Some(x) = &self.buckets[group][bucket] {
+                    if seen.insert(slot) {
+                        sample.push(x);
+                    }
+                }
+            }
+            return sample;
+        }
+        let mut ranks = rand::seq::index::sample(rng, self.size, k).into_vec();
+        ranks.sort_unstable();
+        let mut ranks = ranks.into_iter().peekable();
+        self.iter()
+            .enumerate()
+            .filter(|&(rank, _)| ranks.next_if_eq(&rank).is_some())
+            .map(|(_, x)| x)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::cuckoo::CuckooHashTable;
+
+    #[test]
+    fn test_sample_k() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut table = CuckooHashTable::new();
+        for x in 0..1000u32 {
+            table.insert(x);
+        }
+        for k in [0, 1, 10, 500, 501, 999, 1000, 2000] {
+            let sample = table.sample_k(&mut rng, k);
+            assert_eq!(sample.len(), k.min(1000));
+            assert_eq!(sample.iter().collect::<HashSet<_>>().len(), sample.len());
+            assert!(sample.iter().all(|x| table.contains(x)));
+        }
+        table.retain(|&x| x < 3);
+        for k in [1, 2, 3] {
+            let sample = table.sample_k(&mut rng, k);
+            assert_eq!(sample.len(), k);
+            assert_eq!(sample.iter().collect::<HashSet<_>>().len(), k);
+        }
+
+        let mut table = CuckooHashTable::with_capacity(64);
+        for x in 0..10u32 {
+            table.insert(x);
+        }
+        let mut counts = [0; 10];
+        for _ in 0..10_000 {
+            for &x in table.sample_k(&mut rng, 2) {
+                counts[x as usize] += 1;
+            }
+        }
+        assert!(counts.iter().all(|&count| (1800..2200).contains(&count)), "{counts:?}");
+    }
+}
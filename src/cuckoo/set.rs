@@ -0,0 +1,231 @@
+//! The parts of the `std::collections::HashSet` API that the table does
+//! not need for itself, so that it can stand in for a `HashSet` behind a
+//! type alias.
+//!
+//! The set operations take another table with the same storage. Lookups
+//! accept any borrowed form of the element, as `HashSet`'s do.
+
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::mem;
+
+use super::handle::next_epoch;
+use super::{BucketStorage, CuckooHashTable, IntoIter};
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> CuckooHashTable<T, S> {
+    /// Returns the element equal to `x`, if any.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, x: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+    {
+        let (group, bucket) = self.find(x)?;
+        self.buckets[group][bucket].as_ref()
+    }
+
+    /// Removes and returns the element equal to `x`, if any.
+    pub fn take<Q: Hash + Eq + ?Sized>(&mut self, x: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+    {
+        let (group, bucket) = self.find(x)?;
+        self.versions.bump(group, bucket);
+        self.size -= 1;
+        self.buckets[group][bucket].take()
+    }
+
+    /// Inserts `x`, replacing and returning an equal element if there was
+    /// one. A replaced element's slot, and any handle to it, is kept.
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        match self.find(&x) {
+            Some((group, bucket)) => self.buckets[group][bucket].replace(x),
+            None => {
+                self.insert(x);
+                None
+            }
+        }
+    }
+
+    /// Inserts `x` if no equal element is present, and returns a
+    /// reference to the element in the table either way.
+    pub fn get_or_insert(&mut self, x: T) -> &T {
+        let (group, bucket) = match self.find(&x) {
+            Some(position) => position,
+            None => {
+                let probe = x.clone();
+                self.insert(x);
+                self.find(&probe).expect("inserted element must be present")
+            }
+        };
+        self.buckets[group][bucket].as_ref().expect("must not be None")
+    }
+
+    /// Inserts an owned copy of `x` if no equal element is present, and
+    /// returns a reference to the element in the table either way.
+    pub fn get_or_insert_owned<Q>(&mut self, x: &Q) -> &T
+    where
+        Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+        T: Borrow<Q>,
+    {
+        self.get_or_insert_with(x, Q::to_owned)
+    }
+
+    /// Inserts the element `f` computes from `x` if no element equal to
+    /// `x` is present, and returns a reference to the element in the
+    /// table either way. `f` must return an element equal to `x`.
+    pub fn get_or_insert_with<Q, F>(&mut self, x: &Q, f: F) -> &T
+    where
+        Q: Hash + Eq + ?Sized,
+        T: Borrow<Q>,
+        F: FnOnce(&Q) -> T,
+    {
+        if self.find(x).is_none() {
+            self.insert(f(x));
+        }
+        let (group, bucket) = self.find(x).expect("inserted element must be present");
+        self.buckets[group][bucket].as_ref().expect("must not be None")
+    }
+
+    /// Returns `true` if every element of `self` is also in `other`.
+    pub fn is_subset(&self, other: &CuckooHashTable<T, S>) -> bool {
+        self.len() <= other.len() && self.iter().all(|x| other.contains(x))
+    }
+
+    /// Returns `true` if every element of `other` is also in `self`.
+    pub fn is_superset(&self, other: &CuckooHashTable<T, S>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if the two tables have no element in common. The
+    /// smaller table is the one that gets walked.
+    pub fn is_disjoint(&self, other: &CuckooHashTable<T, S>) -> bool {
+        let (small, large) = if self.len() <= other.len() { (self, other) } else { (other, self) };
+        !small.iter().any(|x| large.contains(x))
+    }
+
+    /// Visits the elements of `self` followed by the elements of `other`
+    /// that are not in `self`.
+    pub fn union<'a>(&'a self, other: &'a CuckooHashTable<T, S>) -> impl Iterator<Item = &'a T> {
+        self.iter().chain(other.difference(self))
+    }
+
+    /// Visits the elements that are in both tables, walking the smaller.
+    pub fn intersection<'a>(&'a self, other: &'a CuckooHashTable<T, S>) -> impl Iterator<Item = &'a T> {
+        let (small, large) = if self.len() <= other.len() { (self, other) } else { (other, self) };
+        small.iter().filter(move |x| large.contains(x))
+    }
+
+    /// Visits the elements of `self` that are not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a CuckooHashTable<T, S>) -> impl Iterator<Item = &'a T> {
+        self.iter().filter(move |x| !other.contains(x))
+    }
+
+    /// Visits the elements that are in exactly one of the two tables.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a CuckooHashTable<T, S>) -> impl Iterator<Item = &'a T> {
+        self.difference(other).chain(other.difference(self))
+    }
+
+    /// Removes every element, keeping the capacity, the hash functions
+    /// and the bucket storage itself. Every outstanding `SlotHandle` is
+    /// invalidated. Chunks still shared with a clone or snapshot are
+    /// copied before they are emptied.
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.iter_mut().for_each(|slot| *slot = None);
+        }
+        self.size = 0;
+        self.epoch = next_epoch();
+    }
+
+    /// Removes every element and returns them as an iterator. The table
+    /// is left empty with its capacity and hash functions intact, and
+    /// every outstanding `SlotHandle` is invalidated.
+    pub fn drain(&mut self) -> IntoIter<T, S> {
+        let mut empty = self.empty_like(self.capacity);
+        empty.stats = self.stats;
+        mem::replace(self, empty).into_iter()
+    }
+
+    /// Shrinks the table as far as `reserve` would have grown it for the
+    /// current elements, but not below `min_capacity` buckets per group.
+    /// Does nothing if the table is already that small.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let new_capacity = self.capacity_for(self.size).max(min_capacity).max(1);
+        if new_capacity < self.capacity {
+            self.rehash_to(new_capacity);
+        }
+    }
+
+    /// Like `shrink_to`, with no lower bound other than the elements.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+}
+
+/// Two tables are equal if they hold the same elements, regardless of
+/// where those elements sit.
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> PartialEq for CuckooHashTable<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<T: Hash + Clone + Eq, S: BucketStorage<T>> Eq for CuckooHashTable<T, S> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::cuckoo::CuckooHashTable;
+
+    #[test]
+    fn test_hash_set_api() {
+        let evens: CuckooHashTable<u32> = (0..100).step_by(2).collect();
+        let threes: CuckooHashTable<u32> = (0..100).step_by(3).collect();
+        let sixes: CuckooHashTable<u32> = (0..100).step_by(6).collect();
+        assert!(sixes.is_subset(&evens) && evens.is_superset(&sixes));
+        assert!(!evens.is_subset(&threes));
+        assert!(!evens.is_disjoint(&threes));
+        assert!(evens.is_disjoint(&(1..100).step_by(2).collect()));
+
+        assert_eq!(evens.intersection(&threes).copied().collect::<CuckooHashTable<_>>(), sixes);
+        assert_eq!(evens.union(&threes).count(), 50 + 34 - 17);
+        assert_eq!(evens.difference(&threes).count(), 50 - 17);
+        assert!(evens.symmetric_difference(&threes).all(|x| (x % 2 == 0) != (x % 3 == 0)));
+
+        let mut words: CuckooHashTable<String> = CuckooHashTable::new();
+        assert_eq!(words.get_or_insert_owned("cuckoo"), "cuckoo");
+        assert_eq!(words.get_or_insert("cuckoo".to_owned()), "cuckoo");
+        assert_eq!(words.len(), 1);
+        assert_eq!(words.get("cuckoo").map(String::as_str), Some("cuckoo"));
+        assert_eq!(words.replace("cuckoo".to_owned()).as_deref(), Some("cuckoo"));
+        assert_eq!(words.take("cuckoo").as_deref(), Some("cuckoo"));
+        assert!(words.is_empty() && words.get("cuckoo").is_none());
+    }
+
+    #[test]
+    fn test_clear_and_shrink() {
+        let mut table: CuckooHashTable<u32> = (0..1000).collect();
+        assert!(table.len_before_resize() >= table.len());
+        let mut drained: Vec<u32> = table.drain().collect();
+        drained.sort();
+        assert_eq!(drained, (0..1000).collect::<Vec<_>>());
+        assert!(table.is_empty());
+
+        table.extend(0..1000);
+        let capacity = table.capacity();
+        table.retain(|&x| x < 10);
+        table.shrink_to(64);
+        assert_eq!(table.capacity(), 64);
+        table.shrink_to_fit();
+        assert!(table.capacity() < 64);
+        assert!((0..10).all(|x| table.contains(&x)));
+        table.clear();
+        assert!(table.is_empty() && table.capacity() < capacity);
+
+        let (_, handle) = table.insert_with_handle(7);
+        let first_slot: *const Option<u32> = &table.buckets[0][0];
+        table.clear();
+        assert!(table.is_empty() && table.get_by_handle(handle).is_none());
+        assert!(std::ptr::eq(first_slot, &table.buckets[0][0]));
+        table.insert(7);
+        assert!(table.contains(&7));
+    }
+}
@@ -0,0 +1,190 @@
+//! Slot storage for a single bucket group.
+//!
+//! Slots live in fixed-size chunks behind `Arc`s. Cloning the storage
+//! only bumps reference counts, and the first write to a shared chunk
+//! copies just that chunk. This is what makes table clones and snapshots
+//! cheap.
+
+use std::collections::TryReserveError;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+use std::{iter, slice, vec};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Each chunk holds `1 << CHUNK_SHIFT` slots, except possibly the last.
+pub(super) const CHUNK_SHIFT: usize = 10;
+const CHUNK_MASK: usize = (1 << CHUNK_SHIFT) - 1;
+
+/// The default [`BucketStorage`](super::BucketStorage): slots in
+/// reference-counted chunks that are copied on first write.
+#[derive(Clone)]
+pub struct Slots<T> {
+    chunks: Vec<Arc<[Option<T>]>>,
+    len: usize,
+}
+
+impl<T> Slots<T> {
+    pub(super) fn new(len: usize) -> Self {
+        let chunks = (0..len)
+            .step_by(1 << CHUNK_SHIFT)
+            .map(|start| {
+                let chunk_len = (len - start).min(1 << CHUNK_SHIFT);
+                iter::repeat_with(|| None).take(chunk_len).collect()
+            })
+            .collect();
+        Slots { chunks, len }
+    }
+
+    /// Like `new`, but reports a failed allocation instead of aborting.
+    /// Each chunk is reserved fallibly and then moved into its `Arc`,
+    /// which copies it once more; chunks are small, so only that last
+    /// chunk-sized allocation can still abort.
+    pub(super) fn try_new(len: usize) -> Result<Self, TryReserveError> {
+        let mut chunks = Vec::new();
+        chunks.try_reserve_exact(len.div_ceil(1 << CHUNK_SHIFT))?;
+        for start in (0..len).step_by(1 << CHUNK_SHIFT) {
+            let chunk_len = (len - start).min(1 << CHUNK_SHIFT);
+            let mut chunk = Vec::new();
+            chunk.try_reserve_exact(chunk_len)?;
+            chunk.resize_with(chunk_len, || None);
+            chunks.push(Arc::from(chunk));
+        }
+        Ok(Slots { chunks, len })
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(super) fn iter(&self) -> Iter<'_, T> {
+        Iter { chunks: self.chunks.iter(), current: [].iter() }
+    }
+}
+
+#[cfg(test)]
+impl<T> Slots<T> {
+    /// Number of chunks that are shared with another table.
+    pub(super) fn shared_chunks(&self) -> usize {
+        self.chunks.iter().filter(|chunk| Arc::strong_count(chunk) > 1).count()
+    }
+}
+
+impl<T: Clone> Slots<T> {
+    /// Iterates mutably over every slot, copying any chunk that is still
+    /// shared with another table.
+    pub(super) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Option<T>> {
+        self.chunks.iter_mut().flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Clone + Send + Sync> Slots<T> {
+    pub(super) fn par_chunks(&self) -> impl IndexedParallelIterator<Item = &[Option<T>]> {
+        self.chunks.par_iter().map(|chunk| &**chunk)
+    }
+
+    /// Empties the given slots of every chunk, where `doomed[i]` lists
+    /// the offsets to clear within chunk `i`. Chunks are disjoint, so each
+    /// one is handled on its own thread, and chunks with nothing to clear
+    /// are not copied even if they are shared.
+    pub(super) fn par_clear(&mut self, doomed: &[Vec<usize>]) {
+        self.chunks.par_iter_mut().zip(doomed).for_each(|(chunk, offsets)| {
+            if !offsets.is_empty() {
+                let chunk = Arc::make_mut(chunk);
+                for &offset in offsets {
+                    chunk[offset] = None;
+                }
+            }
+        });
+    }
+
+    pub(super) fn into_par_iter(self) -> impl ParallelIterator<Item = T> {
+        self.chunks.into_par_iter().flat_map_iter(|mut chunk| {
+            (0..chunk.len()).filter_map(move |index| Arc::make_mut(&mut chunk)[index].take())
+        })
+    }
+}
+
+impl<T> Index<usize> for Slots<T> {
+    type Output = Option<T>;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Option<T> {
+        &self.chunks[index >> CHUNK_SHIFT][index & CHUNK_MASK]
+    }
+}
+
+/// Mutable access copies the slot's chunk first if it is shared.
+impl<T: Clone> IndexMut<usize> for Slots<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Option<T> {
+        &mut Arc::make_mut(&mut self.chunks[index >> CHUNK_SHIFT])[index & CHUNK_MASK]
+    }
+}
+
+pub struct Iter<'a, T> {
+    chunks: slice::Iter<'a, Arc<[Option<T>]>>,
+    current: slice::Iter<'a, Option<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a Option<T>;
+
+    fn next(&mut self) -> Option<&'a Option<T>> {
+        loop {
+            if let Some(slot) = self.current.next() {
+                return Some(slot);
+            }
+            self.current = self.chunks.next()?.iter();
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Slots<T> {
+    type Item = &'a Option<T>;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Owning iterator over the slots. Elements of chunks that are still
+/// shared are cloned out, the rest are moved.
+pub struct IntoIter<T> {
+    chunks: vec::IntoIter<Arc<[Option<T>]>>,
+    current: Option<Arc<[Option<T>]>>,
+    index: usize,
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>> {
+        loop {
+            if let Some(chunk) = &mut self.current {
+                if self.index < chunk.len() {
+                    let slot = Arc::make_mut(chunk)[self.index].take();
+                    self.index += 1;
+                    return Some(slot);
+                }
+            }
+            self.current = Some(self.chunks.next()?);
+            self.index = 0;
+        }
+    }
+}
+
+impl<T> IntoIterator for Slots<T>
+where
+    T: Clone,
+{
+    type Item = Option<T>;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { chunks: self.chunks.into_iter(), current: None, index: 0 }
+    }
+}
@@ -0,0 +1,190 @@
+//! Backing storage for the slots of a bucket group.
+
+use std::collections::TryReserveError;
+use std::ops::{Index, IndexMut};
+use std::{slice, vec};
+
+use super::slots::{self, Slots};
+
+/// Storage for the slots of one bucket group of a `CuckooHashTable`.
+///
+/// The table only ever addresses slots by index and walks them in index
+/// order, so anything that can hand out `Option<T>` slots that way can
+/// back a table: the default copy-on-write [`Slots`] or the flat
+/// [`BoxedSlots`]. Storage is built from a length alone, so it has to own
+/// its memory; there is no way to hand it a file or a mapping.
+pub trait BucketStorage<T>: Index<usize, Output = Option<T>> + IndexMut<usize> {
+    type Iter<'a>: Iterator<Item = &'a Option<T>>
+    where
+        Self: 'a,
+        T: 'a;
+    type IntoIter: Iterator<Item = Option<T>>;
+
+    /// Returns storage of `len` empty slots.
+    fn with_len(len: usize) -> Self;
+
+    /// Like `with_len`, but reports a failed allocation instead of
+    /// aborting.
+    fn try_with_len(len: usize) -> Result<Self, TryReserveError>
+    where
+        Self: Sized;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over every slot in index order.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Iterates mutably over every slot in index order.
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut Option<T>>
+    where
+        T: 'a;
+
+    /// Consumes the storage, yielding every slot in index order.
+    fn into_slots(self) -> Self::IntoIter;
+}
+
+impl<T: Clone> BucketStorage<T> for Slots<T> {
+    type Iter<'a>
+        = slots::Iter<'a, T>
+    where
+        T: 'a;
+    type IntoIter = slots::IntoIter<T>;
+
+    fn with_len(len: usize) -> Self {
+        Slots::new(len)
+    }
+
+    fn try_with_len(len: usize) -> Result<Self, TryReserveError> {
+        Slots::try_new(len)
+    }
+
+    fn len(&self) -> usize {
+        Slots::len(self)
+    }
+
+    fn iter(&self) -> slots::Iter<'_, T> {
+        Slots::iter(self)
+    }
+
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut Option<T>>
+    where
+        T: 'a,
+    {
+        Slots::iter_mut(self)
+    }
+
+    fn into_slots(self) -> slots::IntoIter<T> {
+        self.into_iter()
+    }
+}
+
+/// Slots in one contiguous boxed slice. Cloning copies every slot, but
+/// indexing skips the chunk lookup of [`Slots`].
+///
+/// With the `hugepages` feature on Linux, the slice is advised to use
+/// transparent huge pages. The chunks of [`Slots`] are far smaller than a
+/// huge page, so this is the storage for tables large enough to suffer
+/// TLB misses.
+#[derive(Clone)]
+pub struct BoxedSlots<T>(Box<[Option<T>]>);
+
+impl<T> BoxedSlots<T> {
+    /// Fills the freshly reserved `slots` with `len` empty slots.
+    fn fill(mut slots: Vec<Option<T>>, len: usize) -> Self {
+        #[cfg(all(feature = "hugepages", target_os = "linux"))]
+        crate::hugepages::advise(slots.spare_capacity_mut());
+        slots.resize_with(len, || None);
+        BoxedSlots(slots.into_boxed_slice())
+    }
+}
+
+impl<T> Index<usize> for BoxedSlots<T> {
+    type Output = Option<T>;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Option<T> {
+        &self.0[index]
+    }
+}
+
+impl<T> IndexMut<usize> for BoxedSlots<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Option<T> {
+        &mut self.0[index]
+    }
+}
+
+impl<T> BucketStorage<T> for BoxedSlots<T> {
+    type Iter<'a>
+        = slice::Iter<'a, Option<T>>
+    where
+        T: 'a;
+    type IntoIter = vec::IntoIter<Option<T>>;
+
+    fn with_len(len: usize) -> Self {
+        BoxedSlots::fill(Vec::with_capacity(len), len)
+    }
+
+    fn try_with_len(len: usize) -> Result<Self, TryReserveError> {
+        let mut slots = Vec::new();
+        slots.try_reserve_exact(len)?;
+        Ok(BoxedSlots::fill(slots, len))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter(&self) -> slice::Iter<'_, Option<T>> {
+        self.0.iter()
+    }
+
+    fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut Option<T>>
+    where
+        T: 'a,
+    {
+        self.0.iter_mut()
+    }
+
+    fn into_slots(self) -> vec::IntoIter<Option<T>> {
+        self.0.into_vec().into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cuckoo::{BoxedSlots, BucketStorage, CuckooHashTable, Slots};
+
+    fn exercise<S: BucketStorage<u32> + Clone>() {
+        let mut table = CuckooHashTable::<u32, S>::with_storage(4);
+        for x in 0..1000 {
+            assert!(table.insert(x));
+        }
+        assert!(table.stats().resizes > 0);
+        assert_eq!(table.iter().len(), 1000);
+        table.retain(|x| x % 2 == 0);
+        let odd = table.split_off(|x| x % 4 == 2);
+        assert_eq!(odd.len(), 250);
+        assert!((0..1000).all(|x| table.contains(&x) == (x % 4 == 0)));
+        let mut owned: Vec<u32> = table.into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, (0..1000).step_by(4).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_storages_behave_alike() {
+        exercise::<Slots<u32>>();
+        exercise::<BoxedSlots<u32>>();
+    }
+
+    #[test]
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    fn test_huge_page_boxed_slots() {
+        let slots = BoxedSlots::<u64>::with_len(1 << 20);
+        assert!(crate::hugepages::is_advised(&slots[1 << 19]));
+    }
+}
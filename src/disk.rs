@@ -0,0 +1,598 @@
+//! A cuckoo hash index over fixed-size records in a file.
+//!
+//! The file starts with a header page followed by the bucket pages. Each
+//! bucket page holds as many records as fit, and every record is an
+//! occupied flag, the key and the value. A key can live in any slot of
+//! either of its two pages. Inserting into two full pages displaces a
+//! record to its other page, one page read and write at a time, just as
+//! `CuckooHashTable` moves elements between its bucket groups. The index
+//! does not grow; create it with enough pages up front.
+//!
+//! Pages are picked with a hash function defined here rather than the
+//! standard library's, whose output may change between Rust releases and
+//! would strand existing files.
+//!
+//! Writes are not atomic. An insertion that displaces records rewrites
+//! several pages one after another, and the record count in the header
+//! is written before them, so a crash partway through can leave a record
+//! on two pages or on none, or the count off by one, even with
+//! [`SyncMode::EveryWrite`]. A read or write that fails without a crash
+//! is rolled back as far as the file still allows.
+
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+
+use crate::cuckoo::{random_hasher, xorshift};
+use crate::error::{CuckooError, DiskError};
+use crate::kick::{self, SlotBuckets};
+
+const MAGIC: [u8; 8] = *b"CUCKOOIX";
+const VERSION: u32 = 1;
+/// Magic, version, page size, key and value length, page count, two
+/// seeds and the number of records.
+const HEADER_LEN: usize = 56;
+const LEN_OFFSET: u64 = 48;
+const MAX_KICKS: usize = 100;
+const OCCUPIED: u8 = 1;
+
+/// When the index asks the OS to flush its writes to the device.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Never on its own; call [`DiskIndex::sync`] at checkpoints.
+    #[default]
+    Manual,
+    /// After every insertion and removal, before it returns. This makes
+    /// finished operations durable, not interrupted ones atomic.
+    EveryWrite,
+}
+
+/// Shape of a new index, passed to [`DiskIndex::create`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskOptions {
+    pub key_len: usize,
+    pub value_len: usize,
+    /// Number of bucket pages, fixed for the life of the file.
+    pub pages: u64,
+    pub page_size: usize,
+    pub sync: SyncMode,
+}
+
+impl DiskOptions {
+    /// Options for `pages` bucket pages of 4 KiB holding `key_len`-byte
+    /// keys with `value_len`-byte values.
+    pub fn new(key_len: usize, value_len: usize, pages: u64) -> Self {
+        DiskOptions { key_len, value_len, pages, page_size: 4096, sync: SyncMode::Manual }
+    }
+
+    pub fn with_page_size(self, page_size: usize) -> Self {
+        DiskOptions { page_size, ..self }
+    }
+
+    pub fn with_sync(self, sync: SyncMode) -> Self {
+        DiskOptions { sync, ..self }
+    }
+}
+
+/// A cuckoo hash index stored in a file, mapping fixed-length byte keys
+/// to fixed-length byte values.
+pub struct DiskIndex {
+    file: File,
+    key_len: usize,
+    value_len: usize,
+    pages: u64,
+    page_size: usize,
+    seeds: [u64; 2],
+    len: u64,
+    sync: SyncMode,
+    rng: u64,
+}
+
+/// A bucket page read into memory.
+struct Page {
+    index: u64,
+    bytes: Vec<u8>,
+}
+
+impl DiskIndex {
+    /// Creates a new, empty index file at `path`. Fails if the file
+    /// already exists, and with [`DiskError::InvalidOptions`] if `options`
+    /// has no pages, if a page cannot hold the header or a single record,
+    /// or if the page size, key length or value length does not fit in the
+    /// 32 bits the header stores it in.
+    pub fn create<P: AsRef<Path>>(path: P, options: DiskOptions) -> Result<Self, DiskError> {
+        let lengths = [options.page_size, options.key_len, options.value_len];
+        if lengths.iter().any(|&len| u32::try_from(len).is_err()) {
+            return Err(DiskError::InvalidOptions("page size, key and value length must fit in 32 bits"));
+        }
+        let record_len = record_len(options.key_len, options.value_len).ok_or(CuckooError::CapacityOverflow)?;
+        if options.pages == 0 {
+            return Err(DiskError::InvalidOptions("an index needs at least one page"));
+        }
+        if options.page_size < HEADER_LEN.max(record_len) {
+            return Err(DiskError::InvalidOptions("a page cannot hold the header and a record"));
+        }
+        let file_len = file_len(options.pages, options.page_size).ok_or(CuckooError::CapacityOverflow)?;
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(path)?;
+        let index = DiskIndex {
+            file,
+            key_len: options.key_len,
+            value_len: options.value_len,
+            pages: options.pages,
+            page_size: options.page_size,
+            seeds: [random_hasher().finish(), random_hasher().finish()],
+            len: 0,
+            sync: options.sync,
+            rng: random_hasher().finish() | 1,
+        };
+        // Pages past the header read back as zeros, which is an empty slot.
+        index.file.set_len(file_len)?;
+        write_at(&index.file, &index.header(), 0)?;
+        index.file.sync_all()?;
+        Ok(index)
+    }
+
+    /// Opens an index created by [`DiskIndex::create`].
+    pub fn open<P: AsRef<Path>>(path: P, sync: SyncMode) -> Result<Self, DiskError> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut header = [0; HEADER_LEN];
+        read_at(&file, &mut header, 0).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => DiskError::InvalidFormat("file is shorter than the header"),
+            _ => e.into(),
+        })?;
+        if header[..8] != MAGIC {
+            return Err(DiskError::InvalidFormat("bad magic number"));
+        }
+        let field = |offset: usize, len: usize| {
+            let mut bytes = [0; 8];
+            bytes[..len].copy_from_slice(&header[offset..offset + len]);
+            u64::from_le_bytes(bytes)
+        };
+        if field(8, 4) != u64::from(VERSION) {
+            return Err(DiskError::InvalidFormat("unsupported version"));
+        }
+        let index = DiskIndex {
+            file,
+            page_size: field(12, 4) as usize,
+            key_len: field(16, 4) as usize,
+            value_len: field(20, 4) as usize,
+            pages: field(24, 8),
+            seeds: [field(32, 8), field(40, 8)],
+            len: field(48, 8),
+            sync,
+            rng: random_hasher().finish() | 1,
+        };
+        // The header is untrusted, so nothing may be computed from it
+        // before it is known not to overflow.
+        let (Some(record_len), Some(file_len)) =
+            (record_len(index.key_len, index.value_len), file_len(index.pages, index.page_size))
+        else {
+            return Err(DiskError::InvalidFormat("page layout overflows"));
+        };
+        if index.pages == 0 || index.page_size < HEADER_LEN.max(record_len) {
+            return Err(DiskError::InvalidFormat("inconsistent page layout"));
+        }
+        if index.len > index.capacity() {
+            return Err(DiskError::InvalidFormat("more records than slots"));
+        }
+        if index.file.metadata()?.len() < file_len {
+            return Err(DiskError::InvalidFormat("file is shorter than its pages"));
+        }
+        Ok(index)
+    }
+
+    /// Returns the number of records in the index.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of record slots over all pages.
+    pub fn capacity(&self) -> u64 {
+        self.pages * self.slots_per_page() as u64
+    }
+
+    /// Returns the value stored for `key`.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DiskError> {
+        self.check_len(key, self.key_len)?;
+        for index in self.pages_of(key) {
+            let page = self.read_page(index)?;
+            if let Some(slot) = self.find(&page, key) {
+                return Ok(Some(self.value(&page, slot).to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn contains(&self, key: &[u8]) -> Result<bool, DiskError> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Stores `value` for `key` and returns the value it replaced, if any.
+    /// Returns [`CuckooError::InsertionFailed`] without changing the index
+    /// if no slot could be freed for a new key.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, DiskError> {
+        self.check_len(key, self.key_len)?;
+        self.check_len(value, self.value_len)?;
+        let [p0, p1] = self.pages_of(key);
+        let mut pages = [self.read_page(p0)?, self.read_page(p1)?];
+        for page in &mut pages {
+            if let Some(slot) = self.find(page, key) {
+                let old = self.value(page, slot).to_vec();
+                self.record_mut(page, slot)[1 + self.key_len..].copy_from_slice(value);
+                self.write_page(page)?;
+                self.finish_write()?;
+                return Ok(Some(old));
+            }
+        }
+        let mut record = vec![OCCUPIED];
+        record.extend_from_slice(key);
+        record.extend_from_slice(value);
+        // The count goes to the file first, so that a failed insertion
+        // only has to put it back and never leaves it behind the records.
+        self.write_len(self.len + 1)?;
+        if let Err(err) = self.place_record(pages, record) {
+            let _ = self.write_len(self.len);
+            return Err(err);
+        }
+        self.len += 1;
+        self.finish_write()?;
+        Ok(None)
+    }
+
+    /// Removes `key` and returns its value, if it was present.
+    pub fn remove(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DiskError> {
+        self.check_len(key, self.key_len)?;
+        for index in self.pages_of(key) {
+            let mut page = self.read_page(index)?;
+            if let Some(slot) = self.find(&page, key) {
+                let old = self.value(&page, slot).to_vec();
+                self.record_mut(&mut page, slot).fill(0);
+                self.write_len(self.len - 1)?;
+                if let Err(err) = self.write_page(&page) {
+                    let _ = self.write_len(self.len);
+                    return Err(err.into());
+                }
+                self.len -= 1;
+                self.finish_write()?;
+                return Ok(Some(old));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Flushes all writes so far to the device.
+    pub fn sync(&self) -> Result<(), DiskError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Writes `record` to a free slot of one of its `pages`, or runs the
+    /// kick chain from the first page if both are full.
+    fn place_record(&mut self, mut pages: [Page; 2], record: Vec<u8>) -> Result<(), DiskError> {
+        for page in &mut pages {
+            if let Some(slot) = self.free_slot(page) {
+                self.record_mut(page, slot).copy_from_slice(&record);
+                self.write_page(page)?;
+                return Ok(());
+            }
+        }
+        self.displace(record, pages[0].index)
+    }
+
+    /// Runs the kick chain for `record` starting at page `start`. If the
+    /// chain runs out, or a read or write fails partway, the swaps made
+    /// so far are undone in reverse so no record is lost.
+    fn displace(&mut self, mut record: Vec<u8>, start: u64) -> Result<(), DiskError> {
+        let mut path = Vec::with_capacity(MAX_KICKS);
+        match kick::kick(self, start, &mut record, MAX_KICKS, |index, slot| path.push((index, slot))) {
+            Ok(None) => Ok(()),
+            Ok(Some(_)) => {
+                kick::undo_kicks(self, &mut record, &path)?;
+                Err(CuckooError::InsertionFailed { displacements: MAX_KICKS as u64 }.into())
+            }
+            Err(err) => {
+                // Whatever broke the chain may break the undo as well, and
+                // the first error is the one worth reporting.
+                let _ = kick::undo_kicks(self, &mut record, &path);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Swaps `record` with the one in `slot` and writes the page back. If
+    /// the write fails, `record` is swapped back so that it still holds
+    /// the record being carried.
+    fn swap_record(&self, page: &mut Page, slot: usize, record: &mut [u8]) -> io::Result<()> {
+        self.record_mut(page, slot).swap_with_slice(record);
+        let written = self.write_page(page);
+        if written.is_err() {
+            self.record_mut(page, slot).swap_with_slice(record);
+        }
+        written
+    }
+
+    fn finish_write(&self) -> Result<(), DiskError> {
+        match self.sync {
+            SyncMode::Manual => Ok(()),
+            SyncMode::EveryWrite => self.sync(),
+        }
+    }
+
+    fn header(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(self.page_size as u32).to_le_bytes());
+        header[16..20].copy_from_slice(&(self.key_len as u32).to_le_bytes());
+        header[20..24].copy_from_slice(&(self.value_len as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&self.pages.to_le_bytes());
+        header[32..40].copy_from_slice(&self.seeds[0].to_le_bytes());
+        header[40..48].copy_from_slice(&self.seeds[1].to_le_bytes());
+        header[48..56].copy_from_slice(&self.len.to_le_bytes());
+        header
+    }
+
+    fn write_len(&self, len: u64) -> io::Result<()> {
+        write_at(&self.file, &len.to_le_bytes(), LEN_OFFSET)
+    }
+
+    fn check_len(&self, bytes: &[u8], expected: usize) -> Result<(), DiskError> {
+        if bytes.len() == expected {
+            Ok(())
+        } else {
+            Err(DiskError::WrongLength { expected, actual: bytes.len() })
+        }
+    }
+
+    fn pages_of(&self, key: &[u8]) -> [u64; 2] {
+        self.seeds.map(|seed| stable_hash(seed, key) % self.pages)
+    }
+
+    fn record_len(&self) -> usize {
+        1 + self.key_len + self.value_len
+    }
+
+    fn slots_per_page(&self) -> usize {
+        self.page_size / self.record_len()
+    }
+
+    /// Byte offset of bucket page `index`. Page 0 of the file is the
+    /// header.
+    fn page_offset(&self, index: u64) -> u64 {
+        (index + 1) * self.page_size as u64
+    }
+
+    fn read_page(&self, index: u64) -> io::Result<Page> {
+        let mut bytes = vec![0; self.page_size];
+        read_at(&self.file, &mut bytes, self.page_offset(index))?;
+        Ok(Page { index, bytes })
+    }
+
+    fn write_page(&self, page: &Page) -> io::Result<()> {
+        write_at(&self.file, &page.bytes, self.page_offset(page.index))
+    }
+
+    fn record<'a>(&self, page: &'a Page, slot: usize) -> &'a [u8] {
+        let start = slot * self.record_len();
+        &page.bytes[start..start + self.record_len()]
+    }
+
+    fn record_mut<'a>(&self, page: &'a mut Page, slot: usize) -> &'a mut [u8] {
+        let start = slot * self.record_len();
+        &mut page.bytes[start..start + self.record_len()]
+    }
+
+    fn value<'a>(&self, page: &'a Page, slot: usize) -> &'a [u8] {
+        &self.record(page, slot)[1 + self.key_len..]
+    }
+
+    fn find(&self, page: &Page, key: &[u8]) -> Option<usize> {
+        (0..self.slots_per_page()).find(|&slot| {
+            let record = self.record(page, slot);
+            record[0] == OCCUPIED && &record[1..1 + self.key_len] == key
+        })
+    }
+
+    fn free_slot(&self, page: &Page) -> Option<usize> {
+        (0..self.slots_per_page()).find(|&slot| self.record(page, slot)[0] != OCCUPIED)
+    }
+}
+
+/// The bucket pages, with records carried as their raw bytes.
+impl SlotBuckets for DiskIndex {
+    type Bucket = u64;
+    type Entry = Vec<u8>;
+    type Error = io::Error;
+
+    fn random_slot(&mut self) -> usize {
+        (xorshift(&mut self.rng) % self.slots_per_page() as u64) as usize
+    }
+
+    fn alternate(&self, index: u64, record: &Vec<u8>) -> u64 {
+        let [a, b] = self.pages_of(&record[1..1 + self.key_len]);
+        if a == index {
+            b
+        } else {
+            a
+        }
+    }
+
+    fn place(&mut self, index: u64, record: &mut Vec<u8>) -> io::Result<bool> {
+        let mut page = self.read_page(index)?;
+        let Some(slot) = self.free_slot(&page) else { return Ok(false) };
+        self.record_mut(&mut page, slot).copy_from_slice(record);
+        self.write_page(&page)?;
+        Ok(true)
+    }
+
+    fn swap(&mut self, index: u64, slot: usize, record: &mut Vec<u8>) -> io::Result<()> {
+        let mut page = self.read_page(index)?;
+        self.swap_record(&mut page, slot, record)
+    }
+}
+
+/// Length of a record: the occupied flag, the key and the value.
+fn record_len(key_len: usize, value_len: usize) -> Option<usize> {
+    key_len.checked_add(value_len)?.checked_add(1)
+}
+
+/// Length of a file with the header page and `pages` bucket pages.
+fn file_len(pages: u64, page_size: usize) -> Option<u64> {
+    pages.checked_add(1)?.checked_mul(page_size as u64)
+}
+
+/// 64-bit FNV-1a over `key`, starting from a basis perturbed by `seed`
+/// and finished with the splitmix64 mixer so every output bit depends on
+/// every input bit. Its output is part of the file format.
+fn stable_hash(seed: u64, key: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for &byte in key {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ hash >> 31
+}
+
+// Positioned reads and writes leave no shared cursor behind, so lookups
+// can take `&self`.
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(not(unix))]
+fn read_at(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+#[cfg(not(unix))]
+fn write_at(mut file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::disk::{write_at, DiskIndex, DiskOptions, SyncMode};
+    use crate::error::{CuckooError, DiskError};
+
+    /// A path in the temporary directory that is removed again on drop.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("cuckoo-rs-{}-{name}", std::process::id()));
+            let _ = fs::remove_file(&path);
+            TempPath(path)
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_insert_get_remove_and_reopen() {
+        let path = TempPath::new("reopen");
+        let options = DiskOptions::new(8, 4, 64).with_page_size(512);
+        let mut index = DiskIndex::create(&path.0, options).unwrap();
+        assert!(DiskIndex::create(&path.0, options).is_err());
+        for x in 0..1000u64 {
+            assert_eq!(index.insert(&x.to_le_bytes(), &(x as u32).to_le_bytes()).unwrap(), None);
+        }
+        assert_eq!(index.len(), 1000);
+        assert_eq!(index.insert(&7u64.to_le_bytes(), &[9; 4]).unwrap(), Some(7u32.to_le_bytes().to_vec()));
+        assert_eq!(index.remove(&8u64.to_le_bytes()).unwrap(), Some(8u32.to_le_bytes().to_vec()));
+        assert_eq!(index.remove(&8u64.to_le_bytes()).unwrap(), None);
+        drop(index);
+
+        let index = DiskIndex::open(&path.0, SyncMode::EveryWrite).unwrap();
+        assert_eq!(index.len(), 999);
+        assert_eq!(index.get(&7u64.to_le_bytes()).unwrap(), Some(vec![9; 4]));
+        assert!(!index.contains(&8u64.to_le_bytes()).unwrap());
+        for x in (0..1000u64).filter(|&x| x != 7 && x != 8) {
+            assert_eq!(index.get(&x.to_le_bytes()).unwrap(), Some((x as u32).to_le_bytes().to_vec()));
+        }
+        assert!(matches!(
+            index.get(&[0; 3]),
+            Err(DiskError::WrongLength { expected: 8, actual: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_full_index_keeps_records() {
+        let path = TempPath::new("full");
+        let options = DiskOptions::new(4, 0, 4).with_page_size(64);
+        let mut index = DiskIndex::create(&path.0, options).unwrap();
+        let mut inserted = Vec::new();
+        for x in 0..100u32 {
+            match index.insert(&x.to_le_bytes(), &[]) {
+                Ok(_) => inserted.push(x),
+                Err(DiskError::Cuckoo(CuckooError::InsertionFailed { .. })) => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert!(inserted.len() < 100);
+        assert_eq!(index.len(), inserted.len() as u64);
+        assert!(index.len() <= index.capacity());
+        for x in 0..100u32 {
+            assert_eq!(index.contains(&x.to_le_bytes()).unwrap(), inserted.contains(&x), "{x}");
+        }
+    }
+
+    #[test]
+    fn test_open_rejects_other_files() {
+        let path = TempPath::new("garbage");
+        fs::write(&path.0, [0u8; 100]).unwrap();
+        assert!(matches!(DiskIndex::open(&path.0, SyncMode::Manual), Err(DiskError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_open_rejects_overflowing_header() {
+        let path = TempPath::new("overflow");
+        drop(DiskIndex::create(&path.0, DiskOptions::new(8, 8, 4)).unwrap());
+        let file = fs::OpenOptions::new().write(true).open(&path.0).unwrap();
+        // The page count and the record count, with their valid values.
+        for (offset, valid) in [(24, 4u64), (48, 0)] {
+            write_at(&file, &u64::MAX.to_le_bytes(), offset).unwrap();
+            assert!(matches!(DiskIndex::open(&path.0, SyncMode::Manual), Err(DiskError::InvalidFormat(_))));
+            write_at(&file, &valid.to_le_bytes(), offset).unwrap();
+        }
+        assert!(DiskIndex::open(&path.0, SyncMode::Manual).is_ok());
+    }
+
+    #[test]
+    fn test_create_rejects_invalid_options() {
+        let path = TempPath::new("invalid-options");
+        for options in [
+            DiskOptions::new(1 << 32, 0, 1).with_page_size(usize::MAX),
+            DiskOptions::new(8, 8, 0),
+            DiskOptions::new(8, 8, 4).with_page_size(32),
+        ] {
+            assert!(matches!(DiskIndex::create(&path.0, options), Err(DiskError::InvalidOptions(_))));
+            assert!(!path.0.exists());
+        }
+    }
+}
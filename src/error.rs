@@ -0,0 +1,56 @@
+//! Errors returned by the fallible `try_*` operations and the on-disk
+//! index.
+
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum CuckooError {
+    /// An element could not be placed within the displacement budget and
+    /// the table was not allowed to grow. The displacements were undone.
+    #[error("no free slot found after {displacements} displacements")]
+    InsertionFailed { displacements: u64 },
+    /// The requested number of elements or buckets does not fit in a
+    /// `usize`.
+    #[error("capacity overflow")]
+    CapacityOverflow,
+    /// The bucket storage for the requested capacity could not be
+    /// allocated.
+    #[error("cannot allocate {bytes} bytes of bucket storage")]
+    AllocationFailed { bytes: usize },
+    /// A `SlotHandle` was issued before the table was last resized,
+    /// rehashed or emptied in bulk, or by another table.
+    #[error("handle is from another table or an earlier generation of this one")]
+    StaleGeneration,
+    /// The element a `SlotHandle` referred to has since been moved to
+    /// another slot or removed.
+    #[error("the element behind the handle was moved or removed")]
+    StaleSlot,
+}
+
+/// Errors returned by [`crate::disk::DiskIndex`].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DiskError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file is not an index, or was written by an incompatible
+    /// version of the format.
+    #[error("invalid index file: {0}")]
+    InvalidFormat(&'static str),
+    /// The options passed to [`crate::disk::DiskIndex::create`] do not
+    /// describe a usable index.
+    #[error("invalid index options: {0}")]
+    InvalidOptions(&'static str),
+    /// A key or value does not have the length the index was created
+    /// with.
+    #[error("expected {expected} bytes, got {actual}")]
+    WrongLength { expected: usize, actual: usize },
+    /// The index could not be laid out or take another record. An
+    /// insertion that finds no free slot returns
+    /// [`CuckooError::InsertionFailed`] and leaves the index as it was.
+    #[error(transparent)]
+    Cuckoo(#[from] CuckooError),
+}
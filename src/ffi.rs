@@ -0,0 +1,107 @@
+//! C interface to the crate.
+//!
+//! The table is exposed as an opaque `CuckooTable` handle holding `u64`
+//! keys. Every handle returned by `cuckoo_table_new` must be released
+//! with `cuckoo_table_free`. Enabling the `cbindgen` feature generates
+//! the header from this module into the build's `OUT_DIR`; building with
+//! `CUCKOO_RS_UPDATE_HEADER=1` also refreshes the checked-in
+//! `include/cuckoo_rs.h`.
+
+use crate::cuckoo::CuckooHashTable;
+
+/// Opaque handle to a cuckoo hash table of `u64` keys.
+pub struct CuckooTable(CuckooHashTable<u64>);
+
+/// Allocates a new, empty table.
+#[no_mangle]
+pub extern "C" fn cuckoo_table_new() -> *mut CuckooTable {
+    Box::into_raw(Box::new(CuckooTable(CuckooHashTable::new())))
+}
+
+/// Inserts `key` and returns `true` if it was not already present.
+/// Returns `false` if `table` is null.
+///
+/// # Safety
+///
+/// `table` must be null or a pointer returned by `cuckoo_table_new`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_table_insert(table: *mut CuckooTable, key: u64) -> bool {
+    match table.as_mut() {
+        Some(table) => table.0.insert(key),
+        None => false,
+    }
+}
+
+/// Returns `true` if `key` is present. Returns `false` if `table` is null.
+///
+/// # Safety
+///
+/// `table` must be null or a pointer returned by `cuckoo_table_new`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_table_contains(table: *const CuckooTable, key: u64) -> bool {
+    match table.as_ref() {
+        Some(table) => table.0.contains(&key),
+        None => false,
+    }
+}
+
+/// Removes `key` and returns `true` if it was present. Returns `false`
+/// if `table` is null.
+///
+/// # Safety
+///
+/// `table` must be null or a pointer returned by `cuckoo_table_new`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_table_remove(table: *mut CuckooTable, key: u64) -> bool {
+    match table.as_mut() {
+        Some(table) => table.0.remove(&key),
+        None => false,
+    }
+}
+
+/// Releases a table. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `table` must be null or a pointer returned by `cuckoo_table_new`,
+/// and it must not be used again after this call.
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_table_free(table: *mut CuckooTable) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use crate::ffi::*;
+
+    #[test]
+    fn test_table_round_trip() {
+        unsafe {
+            let table = cuckoo_table_new();
+            assert!(cuckoo_table_insert(table, 42));
+            assert!(!cuckoo_table_insert(table, 42));
+            assert!(cuckoo_table_contains(table, 42));
+            assert!(!cuckoo_table_contains(table, 7));
+            assert!(cuckoo_table_remove(table, 42));
+            assert!(!cuckoo_table_contains(table, 42));
+            cuckoo_table_free(table);
+        }
+    }
+
+    #[test]
+    fn test_null_table() {
+        unsafe {
+            assert!(!cuckoo_table_insert(ptr::null_mut(), 1));
+            assert!(!cuckoo_table_contains(ptr::null(), 1));
+            assert!(!cuckoo_table_remove(ptr::null_mut(), 1));
+            cuckoo_table_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,648 @@
+//! Approximate membership structures built on partial-key cuckoo hashing.
+//!
+//! A filter stores a short fingerprint of every key instead of the key
+//! itself. Lookups can return false positives at a small, bounded rate
+//! but never false negatives for keys that were inserted and not removed.
+
+use std::convert::Infallible;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Index, IndexMut};
+
+use crate::cuckoo::{random_hasher, seeded_hasher, xorshift};
+use crate::kick::{self, SlotBuckets};
+
+pub use self::adaptive::AdaptiveCuckooFilter;
+pub use self::scalable::{Deduplicator, ScalableCuckooFilter};
+
+mod adaptive;
+mod scalable;
+
+/// Slots per bucket. Four slots let a filter reach about 95% occupancy.
+const SLOTS: usize = 4;
+/// Largest fraction of slots a freshly sized filter is expected to fill.
+const TARGET_LOAD: f64 = 0.95;
+const MAX_KICKS: usize = 500;
+const RNG_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+const FINGERPRINT_BITS: u32 = 12;
+const FINGERPRINT_MASK: u16 = (1 << FINGERPRINT_BITS) - 1;
+/// Number of payload bits stored next to each fingerprint in a
+/// [`CuckooMapFilter`].
+pub const VALUE_BITS: u32 = 16 - FINGERPRINT_BITS;
+
+/// An empty slot. Fingerprints are never zero, so neither are occupied
+/// slots.
+const EMPTY: u16 = 0;
+
+type Bucket = [u16; SLOTS];
+
+const CACHE_LINE: usize = 64;
+const BUCKETS_PER_LINE: usize = CACHE_LINE / mem::size_of::<Bucket>();
+const _: () = assert!(CACHE_LINE.is_multiple_of(mem::size_of::<Bucket>()));
+
+/// A cache line's worth of buckets. The alignment guarantees that no
+/// bucket straddles two lines, so probing a bucket costs one cache miss.
+#[repr(align(64))]
+#[derive(Clone, Copy)]
+struct CacheLine([Bucket; BUCKETS_PER_LINE]);
+
+/// Fixed-size array of buckets laid out in whole, aligned cache lines.
+#[derive(Clone)]
+struct Buckets {
+    lines: Vec<CacheLine>,
+    len: usize,
+}
+
+impl Buckets {
+    fn new(len: usize) -> Self {
+        let line = CacheLine([[EMPTY; SLOTS]; BUCKETS_PER_LINE]);
+        let count = len.div_ceil(BUCKETS_PER_LINE);
+        let mut lines = Vec::with_capacity(count);
+        #[cfg(all(feature = "hugepages", target_os = "linux"))]
+        crate::hugepages::advise(lines.spare_capacity_mut());
+        lines.resize(count, line);
+        Buckets { lines, len }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Index<usize> for Buckets {
+    type Output = Bucket;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Bucket {
+        debug_assert!(index < self.len);
+        &self.lines[index / BUCKETS_PER_LINE].0[index % BUCKETS_PER_LINE]
+    }
+}
+
+impl IndexMut<usize> for Buckets {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Bucket {
+        debug_assert!(index < self.len);
+        &mut self.lines[index / BUCKETS_PER_LINE].0[index % BUCKETS_PER_LINE]
+    }
+}
+
+/// A cuckoo filter that keeps a small payload of [`VALUE_BITS`] bits next
+/// to every fingerprint, answering "which value was stored for this key"
+/// approximately.
+///
+/// `get` may return a value for a key that was never inserted (with
+/// probability of roughly `8 / 4096` per lookup). Keys whose fingerprints
+/// collide in a shared bucket keep separate entries, so removing one
+/// leaves the other, but `get` cannot tell them apart and returns the
+/// value of whichever entry it finds first. It is meant for routing
+/// hints, such as a shard id, where an occasional wrong answer is checked
+/// downstream.
+#[derive(Clone)]
+pub struct CuckooMapFilter<T: ?Sized> {
+    buckets: Buckets,
+    /// Entry evicted by a kick chain that ran out of budget. While it is
+    /// set the filter accepts no new keys but still answers for it.
+    victim: Option<(usize, u16)>,
+    /// Occupied slots plus the victim, if any.
+    entries: usize,
+    failures: FailureCounters,
+    hasher: DefaultHasher,
+    rng: u64,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> CuckooMapFilter<T> {
+    /// Creates a filter with room for about `capacity` keys.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, random_hasher())
+    }
+
+    /// Creates a filter whose hash function is derived from `seed`.
+    /// Filters built with the same capacity and seed place every key
+    /// identically.
+    pub fn with_seed(capacity: usize, seed: u64) -> Self {
+        Self::with_hasher(capacity, seeded_hasher(seed))
+    }
+
+    fn with_hasher(capacity: usize, hasher: DefaultHasher) -> Self {
+        CuckooMapFilter {
+            buckets: Buckets::new(bucket_count(capacity)),
+            victim: None,
+            entries: 0,
+            failures: FailureCounters::default(),
+            hasher,
+            rng: RNG_SEED,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Stores `value` for `x` in a new entry. Returns `false` if the filter
+    /// is too full to take the key.
+    ///
+    /// Like any cuckoo filter, this does not check whether `x` is already
+    /// present: inserting a key twice stores two entries, and it stays in
+    /// the filter until it has been removed twice. Use `update` to change
+    /// the value of a key that is already stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not fit in [`VALUE_BITS`] bits.
+    pub fn insert(&mut self, x: &T, value: u8) -> bool {
+        assert!(u32::from(value) < 1 << VALUE_BITS, "value {value} does not fit in {VALUE_BITS} bits");
+        let (i1, fingerprint) = self.index_and_fingerprint(x);
+        let i2 = self.alt_index(i1, fingerprint);
+        let entry = encode(fingerprint, value);
+        if self.victim.is_some() {
+            self.failures.failed();
+            return false;
+        }
+        self.entries += 1;
+        if self.place(i1, entry) || self.place(i2, entry) {
+            self.failures.succeeded();
+            return true;
+        }
+        let index = if self.next_random() & 1 == 0 { i1 } else { i2 };
+        match self.kick(index, entry) {
+            None => self.failures.succeeded(),
+            // The key itself is in the table by now; whatever fell off the
+            // end of the chain is kept as the victim so it is not lost. The
+            // next new key will be rejected, so this counts as a failure.
+            leftover => {
+                self.victim = leftover;
+                self.failures.failed();
+            }
+        }
+        true
+    }
+
+    /// Replaces the value of the entry stored for `x` and returns `true`,
+    /// or returns `false` if there is none. Like `remove`, this can change
+    /// the entry of another key with the same fingerprint instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not fit in [`VALUE_BITS`] bits.
+    pub fn update(&mut self, x: &T, value: u8) -> bool {
+        assert!(u32::from(value) < 1 << VALUE_BITS, "value {value} does not fit in {VALUE_BITS} bits");
+        let (i1, fingerprint) = self.index_and_fingerprint(x);
+        let i2 = self.alt_index(i1, fingerprint);
+        let entry = encode(fingerprint, value);
+        for i in [i1, i2] {
+            if let Some(slot) = self.buckets[i].iter_mut().find(|slot| fingerprint_of(**slot) == fingerprint) {
+                *slot = entry;
+                return true;
+            }
+        }
+        match &mut self.victim {
+            Some((i, victim)) if (*i == i1 || *i == i2) && fingerprint_of(*victim) == fingerprint => {
+                *victim = entry;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Rebuilds the filter with fewer buckets, as long as it keeps room
+    /// for `capacity` keys and for every entry it holds now. Returns
+    /// `false`, leaving the filter unchanged, if it is already that small
+    /// or its entries do not fit in fewer buckets.
+    ///
+    /// Fingerprints and values are kept as they are. Halving the bucket
+    /// count only drops the top bit of every bucket index, and because
+    /// the partner bucket is derived by xor, each entry's two buckets in
+    /// the smaller filter are its old two buckets with that bit dropped.
+    pub fn shrink_to(&mut self, capacity: usize) -> bool {
+        let count = bucket_count(capacity.max(self.entries));
+        if count >= self.buckets.len() {
+            return false;
+        }
+        let mut shrunk = CuckooMapFilter {
+            buckets: Buckets::new(count),
+            victim: None,
+            entries: self.entries,
+            failures: self.failures,
+            hasher: self.hasher.clone(),
+            rng: self.rng,
+            _marker: PhantomData,
+        };
+        let buckets = &self.buckets;
+        let occupied = (0..buckets.len())
+            .flat_map(|i| buckets[i].into_iter().filter(|&entry| entry != EMPTY).map(move |entry| (i, entry)));
+        for (i, entry) in self.victim.into_iter().chain(occupied) {
+            let index = i & shrunk.mask();
+            let alt = shrunk.alt_index(index, fingerprint_of(entry));
+            if shrunk.place(index, entry) || shrunk.place(alt, entry) {
+                continue;
+            }
+            if shrunk.victim.is_some() {
+                return false;
+            }
+            shrunk.victim = shrunk.kick(index, entry);
+        }
+        *self = shrunk;
+        true
+    }
+
+    /// Returns the value stored for `x`, or a value stored for a key with
+    /// a colliding fingerprint.
+    pub fn get(&self, x: &T) -> Option<u8> {
+        let (i1, fingerprint) = self.index_and_fingerprint(x);
+        let i2 = self.alt_index(i1, fingerprint);
+        [i1, i2]
+            .into_iter()
+            .flat_map(|i| self.buckets[i])
+            .chain(self.victim.filter(|(i, _)| *i == i1 || *i == i2).map(|(_, entry)| entry))
+            .find(|&entry| fingerprint_of(entry) == fingerprint)
+            .map(value_of)
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.get(x).is_some()
+    }
+
+    /// Removes the entry for `x`. Removing a key that was never inserted
+    /// can remove the entry of another key with the same fingerprint.
+    pub fn remove(&mut self, x: &T) -> bool {
+        let (i1, fingerprint) = self.index_and_fingerprint(x);
+        let i2 = self.alt_index(i1, fingerprint);
+        if let Some((i, victim)) = self.victim {
+            if (i == i1 || i == i2) && fingerprint_of(victim) == fingerprint {
+                self.victim = None;
+                self.entries -= 1;
+                return true;
+            }
+        }
+        for i in [i1, i2] {
+            if let Some(slot) = self.buckets[i].iter_mut().find(|slot| fingerprint_of(**slot) == fingerprint) {
+                *slot = EMPTY;
+                self.entries -= 1;
+                self.reinsert_victim();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Number of entries held, including the victim. Every successful
+    /// `insert` adds one and every successful `remove` takes one away.
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Number of keys inserted and not removed, counting a key inserted
+    /// twice twice. Keys never share an entry, so this is `len`, with no
+    /// correction for merged keys.
+    pub fn estimated_count(&self) -> f64 {
+        self.entries as f64
+    }
+
+    /// Fraction of slots holding an entry.
+    pub fn load(&self) -> f64 {
+        self.entries as f64 / (self.buckets.len() * SLOTS) as f64
+    }
+
+    /// Expected rate at which `contains` reports a key that was never
+    /// inserted, at the current load: each of the entries in a key's two
+    /// buckets matches its fingerprint with probability `1 / 4095`.
+    pub fn false_positive_rate(&self) -> f64 {
+        false_positive_rate(self.load(), FINGERPRINT_MASK)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn has_victim(&self) -> bool {
+        self.victim.is_some()
+    }
+
+    /// Number of insertions in a row that ran out of room, either by
+    /// being rejected or by pushing an entry into the victim slot. Reset
+    /// by the next insertion that fits.
+    pub fn consecutive_failures(&self) -> u64 {
+        self.failures.consecutive
+    }
+
+    /// Number of insertions that ran out of room since the filter was
+    /// created.
+    pub fn failed_inserts(&self) -> u64 {
+        self.failures.total
+    }
+
+    /// Returns `true` once the filter rejects new keys or has reached the
+    /// load it was sized for. Past this point kick chains get long and
+    /// fail, and the filter should be rebuilt with a larger capacity.
+    pub fn is_saturated(&self) -> bool {
+        self.victim.is_some() || self.load() >= TARGET_LOAD
+    }
+
+    /// Moves `entry` into bucket `index`, evicting a random entry there
+    /// and moving it to its partner bucket, and so on. Returns the entry
+    /// that fell off the end, with its bucket, if the chain ran out.
+    fn kick(&mut self, index: usize, mut entry: u16) -> Option<(usize, u16)> {
+        let Ok(leftover) = kick::kick(self, index, &mut entry, MAX_KICKS, |_, _| {});
+        leftover.map(|index| (index, entry))
+    }
+
+    /// Tries to move the victim back into the table after a removal made
+    /// room.
+    fn reinsert_victim(&mut self) {
+        if let Some((i, entry)) = self.victim {
+            let alt = self.alt_index(i, fingerprint_of(entry));
+            if self.place(i, entry) || self.place(alt, entry) {
+                self.victim = None;
+            }
+        }
+    }
+
+    fn place(&mut self, index: usize, entry: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == EMPTY) {
+            Some(slot) => {
+                *slot = entry;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index_and_fingerprint(&self, x: &T) -> (usize, u16) {
+        let mut hasher = self.hasher.clone();
+        x.hash(&mut hasher);
+        let hash = hasher.finish();
+        let fingerprint = ((hash >> 32) as u16 & FINGERPRINT_MASK).max(1);
+        (hash as usize & self.mask(), fingerprint)
+    }
+
+    /// The partner bucket of `index` for `fingerprint`. Applying it twice
+    /// returns to `index`, which is what lets entries move between their
+    /// two buckets without knowing the original key.
+    fn alt_index(&self, index: usize, fingerprint: u16) -> usize {
+        let spread = u32::from(fingerprint).wrapping_mul(0x5bd1_e995) as usize;
+        (index ^ spread) & self.mask()
+    }
+
+    fn mask(&self) -> usize {
+        self.buckets.len() - 1
+    }
+
+    fn next_random(&mut self) -> u64 {
+        xorshift(&mut self.rng)
+    }
+}
+
+impl<T: Hash + ?Sized> SlotBuckets for CuckooMapFilter<T> {
+    type Bucket = usize;
+    type Entry = u16;
+    type Error = Infallible;
+
+    fn random_slot(&mut self) -> usize {
+        (self.next_random() as usize) % SLOTS
+    }
+
+    fn alternate(&self, index: usize, entry: &u16) -> usize {
+        self.alt_index(index, fingerprint_of(*entry))
+    }
+
+    fn place(&mut self, index: usize, entry: &mut u16) -> Result<bool, Infallible> {
+        Ok(self.place(index, *entry))
+    }
+
+    fn swap(&mut self, index: usize, slot: usize, entry: &mut u16) -> Result<(), Infallible> {
+        mem::swap(&mut self.buckets[index][slot], entry);
+        Ok(())
+    }
+}
+
+/// Tracks insertions that found no room, for saturation reporting.
+#[derive(Clone, Copy, Debug, Default)]
+struct FailureCounters {
+    consecutive: u64,
+    total: u64,
+}
+
+impl FailureCounters {
+    fn succeeded(&mut self) {
+        self.consecutive = 0;
+    }
+
+    fn failed(&mut self) {
+        self.consecutive += 1;
+        self.total += 1;
+    }
+}
+
+/// Number of buckets, always a power of two, needed to hold `capacity`
+/// entries at no more than `TARGET_LOAD`.
+fn bucket_count(capacity: usize) -> usize {
+    ((capacity as f64 / (SLOTS as f64 * TARGET_LOAD)).ceil() as usize)
+        .max(1)
+        .next_power_of_two()
+}
+
+/// Probability that a key matches one of the `2 * SLOTS * load` entries
+/// expected in its two buckets, with `fingerprints` possible values.
+fn false_positive_rate(load: f64, fingerprints: u16) -> f64 {
+    1.0 - (1.0 - 1.0 / f64::from(fingerprints)).powf(2.0 * SLOTS as f64 * load)
+}
+
+fn encode(fingerprint: u16, value: u8) -> u16 {
+    fingerprint << VALUE_BITS | u16::from(value)
+}
+
+fn fingerprint_of(entry: u16) -> u16 {
+    entry >> VALUE_BITS
+}
+
+fn value_of(entry: u16) -> u8 {
+    (entry & ((1 << VALUE_BITS) - 1)) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::mem;
+
+    use crate::filter::{Bucket, Buckets, CuckooMapFilter, CACHE_LINE};
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut filter = CuckooMapFilter::new(100);
+        assert!(filter.insert(&1, 3));
+        assert!(filter.insert(&2, 15));
+        assert_eq!(filter.get(&1), Some(3));
+        assert_eq!(filter.get(&2), Some(15));
+        assert!(filter.update(&1, 7));
+        assert_eq!(filter.get(&1), Some(7));
+        assert_eq!(filter.len(), 2);
+        assert!(filter.remove(&1));
+        assert_eq!(filter.get(&1), None);
+        assert!(!filter.remove(&1));
+        assert!(!filter.update(&1, 7));
+
+        // A key inserted twice stays until it is removed twice.
+        assert!(filter.insert(&3, 1));
+        assert!(filter.insert(&3, 1));
+        assert!(filter.remove(&3));
+        assert!(filter.contains(&3));
+        assert!(filter.remove(&3));
+        assert!(!filter.contains(&3));
+    }
+
+    #[test]
+    fn test_colliding_keys_keep_their_own_entries() {
+        let mut filter = CuckooMapFilter::with_seed(8, 1);
+        let mut seen = HashMap::new();
+        let (a, b) = (0u32..)
+            .find_map(|x| {
+                let (index, fingerprint) = filter.index_and_fingerprint(&x);
+                let first = index.min(filter.alt_index(index, fingerprint));
+                seen.insert((first, fingerprint), x).map(|y| (y, x))
+            })
+            .expect("some keys collide");
+        assert!(filter.insert(&a, 1));
+        assert!(filter.insert(&b, 2));
+        assert_eq!(filter.len(), 2);
+        assert!(filter.remove(&a));
+        assert!(filter.contains(&b));
+        assert!(filter.remove(&b));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_no_false_negatives_near_capacity() {
+        let capacity = 10_000;
+        let mut filter = CuckooMapFilter::with_seed(capacity, 42);
+        for x in 0..capacity as u64 {
+            assert!(filter.insert(&x, (x % 16) as u8), "insert {x} failed");
+        }
+        assert!((0..capacity as u64).all(|x| filter.contains(&x)));
+        // Keys whose fingerprints collide cannot be told apart, so a few
+        // of them report the other key's value.
+        let wrong = (0..capacity as u64).filter(|x| filter.get(x) != Some((x % 16) as u8)).count();
+        assert!(wrong < capacity / 100, "{wrong} wrong values");
+        let false_positives = (capacity as u64..2 * capacity as u64).filter(|x| filter.contains(x)).count();
+        assert!(false_positives < capacity / 100, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_overflow_keeps_inserted_keys() {
+        let mut filter = CuckooMapFilter::with_seed(8, 7);
+        let inserted: Vec<u32> = (0..100).take_while(|x| filter.insert(x, 1)).collect();
+        assert!(inserted.len() < 100);
+        assert!(inserted.iter().all(|x| filter.contains(x)));
+    }
+
+    #[test]
+    fn test_estimated_count() {
+        let mut filter = CuckooMapFilter::with_seed(10_000, 3);
+        assert!(filter.is_empty());
+        assert_eq!(filter.estimated_count(), 0.0);
+        for x in 0..9_000u32 {
+            assert!(filter.insert(&x, 1));
+        }
+        assert_eq!(filter.len(), 9_000);
+        assert_eq!(filter.estimated_count(), 9_000.0);
+        for x in 0..4_000u32 {
+            assert!(filter.remove(&x));
+        }
+        assert_eq!(filter.estimated_count(), 5_000.0);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut filter = CuckooMapFilter::with_seed(10_000, 8);
+        assert_eq!(filter.false_positive_rate(), 0.0);
+        for x in 0..10_000u32 {
+            filter.insert(&x, 0);
+        }
+        let expected = filter.false_positive_rate();
+        let observed = (10_000..110_000u32).filter(|x| filter.contains(x)).count() as f64 / 100_000.0;
+        assert!((observed - expected).abs() < expected / 5.0, "{observed} vs {expected}");
+    }
+
+    #[test]
+    fn test_shrink_keeps_entries() {
+        let mut filter = CuckooMapFilter::with_seed(10_000, 5);
+        for x in 0..10_000u32 {
+            filter.insert(&x, (x % 16) as u8);
+        }
+        for x in 300..10_000u32 {
+            filter.remove(&x);
+        }
+        // A removal can take out the entry of a kept key with a colliding
+        // fingerprint instead, leaving its own entry with another value,
+        // so compare against what is there before shrinking.
+        let values: Vec<_> = (0..300u32).map(|x| filter.get(&x)).collect();
+        let load = filter.load();
+        assert!(filter.shrink_to(500));
+        assert!(filter.load() > load * 8.0);
+        assert!((0..300u32).all(|x| filter.contains(&x)));
+        // Merged buckets can put a colliding fingerprint in front of a
+        // key's own entry, but only rarely.
+        let changed = (0..300u32).filter(|&x| filter.get(&x) != values[x as usize]).count();
+        assert!(changed < 5, "{changed} values changed");
+        assert!(!filter.shrink_to(500));
+        assert!(filter.insert(&20_000, 3));
+        assert_eq!(filter.get(&20_000), Some(3));
+    }
+
+    #[test]
+    fn test_saturation() {
+        let mut filter = CuckooMapFilter::with_seed(8, 7);
+        assert_eq!(filter.load(), 0.0);
+        assert!(!filter.is_saturated());
+        let mut x = 0u32;
+        while filter.failed_inserts() == 0 {
+            filter.insert(&x, 1);
+            x += 1;
+        }
+        assert!(filter.is_saturated());
+        assert!(filter.load() > 0.5);
+        assert!(!filter.insert(&x, 1));
+        assert!(!filter.insert(&(x + 1), 1));
+        assert_eq!(filter.consecutive_failures(), 3);
+        assert_eq!(filter.failed_inserts(), 3);
+        // The first removal lets the victim back in; the second makes
+        // room for a new key.
+        assert!(filter.remove(&0));
+        assert!(filter.remove(&1));
+        assert!(filter.insert(&0, 2));
+        assert_eq!(filter.consecutive_failures(), 0);
+        for y in 0..x {
+            filter.remove(&y);
+        }
+        assert!(!filter.is_saturated());
+        assert_eq!(filter.load(), 0.0);
+    }
+
+    #[test]
+    fn test_buckets_do_not_straddle_cache_lines() {
+        let buckets = Buckets::new(100);
+        for i in 0..buckets.len() {
+            let offset = &buckets[i] as *const Bucket as usize % CACHE_LINE;
+            assert!(offset + mem::size_of::<Bucket>() <= CACHE_LINE);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    fn test_huge_page_buckets() {
+        let mut filter = CuckooMapFilter::with_seed(4 << 20, 9);
+        for x in 0..100_000u32 {
+            assert!(filter.insert(&x, (x % 16) as u8));
+        }
+        assert!((0..100_000u32).all(|x| filter.contains(&x)));
+        let middle = &filter.buckets.lines[filter.buckets.lines.len() / 2];
+        assert!(crate::hugepages::is_advised(middle));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_value_too_large() {
+        CuckooMapFilter::new(1).insert("key", 16);
+    }
+}
@@ -0,0 +1,436 @@
+//! Adaptive cuckoo filter: a filter that fixes its own false positives.
+//!
+//! Each slot stores a fingerprint plus a two-bit hash selector saying
+//! which of four fingerprint functions produced it. The keys themselves
+//! are kept in a parallel array that mirrors the slots. That array plays
+//! the role of the slower backing store, and lookups never touch it.
+//! When a caller learns that a positive answer was wrong, the filter
+//! bumps the selector of the colliding slot and recomputes its fingerprint
+//! from the stored key. After that, repeated queries for the same
+//! non-member stop matching.
+
+use std::convert::Infallible;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::mem;
+
+use super::{
+    bucket_count, false_positive_rate, Buckets, FailureCounters, EMPTY, MAX_KICKS, RNG_SEED, SLOTS, TARGET_LOAD,
+};
+use crate::cuckoo::{random_hasher, seeded_hasher, xorshift};
+use crate::kick::{self, SlotBuckets};
+
+const SELECTOR_SHIFT: u32 = 14;
+const SELECTORS: u16 = 4;
+const FINGERPRINT_MASK: u16 = (1 << SELECTOR_SHIFT) - 1;
+
+/// An adaptive cuckoo filter over keys of type `T`.
+///
+/// `contains` only compares fingerprints and may return false positives.
+/// Report them with [`AdaptiveCuckooFilter::report_false_positive`], and
+/// the filter changes the fingerprint that collided so the same query is
+/// answered correctly from then on. Unlike a plain cuckoo filter this one
+/// keeps a copy of every key, which is what adaptation needs.
+#[derive(Clone)]
+pub struct AdaptiveCuckooFilter<T> {
+    slots: Buckets,
+    keys: Vec<[Option<T>; SLOTS]>,
+    victim: Option<(T, u16)>,
+    len: usize,
+    failures: FailureCounters,
+    index_hasher: DefaultHasher,
+    fingerprint_hasher: DefaultHasher,
+    rng: u64,
+}
+
+impl<T: Hash + Eq + Clone> AdaptiveCuckooFilter<T> {
+    /// Creates a filter with room for about `capacity` keys.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hashers(capacity, random_hasher(), random_hasher())
+    }
+
+    /// Creates a filter whose hash functions are derived from `seed`.
+    pub fn with_seed(capacity: usize, seed: u64) -> Self {
+        Self::with_hashers(capacity, seeded_hasher(seed), seeded_hasher(!seed))
+    }
+
+    fn with_hashers(capacity: usize, index_hasher: DefaultHasher, fingerprint_hasher: DefaultHasher) -> Self {
+        Self::with_buckets(bucket_count(capacity), index_hasher, fingerprint_hasher)
+    }
+
+    fn with_buckets(buckets: usize, index_hasher: DefaultHasher, fingerprint_hasher: DefaultHasher) -> Self {
+        AdaptiveCuckooFilter {
+            slots: Buckets::new(buckets),
+            keys: (0..buckets).map(|_| [const { None }; SLOTS]).collect(),
+            victim: None,
+            len: 0,
+            failures: FailureCounters::default(),
+            index_hasher,
+            fingerprint_hasher,
+            rng: RNG_SEED,
+        }
+    }
+
+    /// Returns `true` if some slot in `x`'s buckets holds a fingerprint
+    /// matching `x`. Only fingerprints are read.
+    pub fn contains(&self, x: &T) -> bool {
+        let (i1, i2) = self.indices(x);
+        let hash = self.fingerprint_hash(x);
+        self.slots[i1].iter().chain(&self.slots[i2]).any(|&slot| matches(slot, hash))
+            || self.victim.as_ref().is_some_and(|(_, slot)| matches(*slot, hash))
+    }
+
+    /// Inserts `x`. Returns `false` if it was already present or the
+    /// filter is too full to take it.
+    pub fn insert(&mut self, x: T) -> bool {
+        if self.find(&x).is_some() || self.victim.as_ref().is_some_and(|(key, _)| *key == x) {
+            return false;
+        }
+        if self.victim.is_some() {
+            self.failures.failed();
+            return false;
+        }
+        let slot = self.encode(&x, 0);
+        self.len += 1;
+        match self.place_or_kick((x, slot)) {
+            None => self.failures.succeeded(),
+            Some(entry) => {
+                // The new key has a slot; the entry left over at the end
+                // of the chain is kept aside so it stays visible to
+                // lookups.
+                self.victim = Some(entry);
+                self.failures.failed();
+            }
+        }
+        true
+    }
+
+    /// Rebuilds the filter with fewer buckets, as long as it keeps room
+    /// for `capacity` keys and for every key it holds now. Returns
+    /// `false`, leaving the filter unchanged, if it is already that small
+    /// or its keys do not fit in fewer buckets.
+    ///
+    /// Every stored key is placed again with the fingerprint it has now,
+    /// so false positives that were already reported stay fixed.
+    pub fn shrink_to(&mut self, capacity: usize) -> bool {
+        let count = bucket_count(capacity.max(self.len));
+        if count >= self.slots.len() {
+            return false;
+        }
+        let mut shrunk = Self::with_buckets(count, self.index_hasher.clone(), self.fingerprint_hasher.clone());
+        shrunk.len = self.len;
+        shrunk.failures = self.failures;
+        shrunk.rng = self.rng;
+        let (keys, slots) = (&self.keys, &self.slots);
+        let stored = (0..slots.len()).flat_map(|index| {
+            (0..SLOTS).filter_map(move |slot| Some((keys[index][slot].clone()?, slots[index][slot])))
+        });
+        for entry in self.victim.clone().into_iter().chain(stored) {
+            if let Some(left) = shrunk.place_or_kick(entry) {
+                if shrunk.victim.is_some() {
+                    return false;
+                }
+                shrunk.victim = Some(left);
+            }
+        }
+        *self = shrunk;
+        true
+    }
+
+    /// Puts `entry` into one of its key's buckets, kicking other entries
+    /// along if both are full. Returns the entry left over when the kicks
+    /// run out.
+    fn place_or_kick(&mut self, entry: (T, u16)) -> Option<(T, u16)> {
+        let (i1, i2) = self.indices(&entry.0);
+        let (key, slot) = match self.place(i1, entry).or_else(|entry| self.place(i2, entry)) {
+            Ok(()) => return None,
+            Err(rejected) => rejected,
+        };
+        let mut entry = (Some(key), slot);
+        let Ok(leftover) = kick::kick(self, i1, &mut entry, MAX_KICKS, |_, _| {});
+        leftover.map(|_| (entry.0.expect("a carried entry has its key"), entry.1))
+    }
+
+    /// Removes `x`. The stored keys are compared, so this never removes
+    /// another key by mistake.
+    pub fn remove(&mut self, x: &T) -> bool {
+        if self.victim.as_ref().is_some_and(|(key, _)| key == x) {
+            self.victim = None;
+            self.len -= 1;
+            return true;
+        }
+        let Some((index, slot)) = self.find(x) else {
+            return false;
+        };
+        self.keys[index][slot] = None;
+        self.slots[index][slot] = EMPTY;
+        self.len -= 1;
+        if let Some(entry) = self.victim.take() {
+            let (i1, i2) = self.indices(&entry.0);
+            if let Err(entry) = self.place(i1, entry).or_else(|entry| self.place(i2, entry)) {
+                self.victim = Some(entry);
+            }
+        }
+        true
+    }
+
+    /// Tells the filter that `contains(x)` returned `true` although `x`
+    /// is not a member. Every slot whose fingerprint matched `x` gets the
+    /// next hash selector. Returns `true` if any slot was changed.
+    ///
+    /// A `true` return does not guarantee that `contains(x)` now returns
+    /// `false`: when every selector of a colliding key yields a
+    /// fingerprint that still matches `x`, that slot keeps matching. Call
+    /// `contains` again if the caller needs to know.
+    pub fn report_false_positive(&mut self, x: &T) -> bool {
+        let (i1, i2) = self.indices(x);
+        let hash = self.fingerprint_hash(x);
+        let mut adapted = false;
+        for index in [i1, i2] {
+            for slot in 0..SLOTS {
+                if !matches(self.slots[index][slot], hash) {
+                    continue;
+                }
+                let Some(key) = &self.keys[index][slot] else { continue };
+                if key == x {
+                    continue;
+                }
+                self.slots[index][slot] = self.next_fingerprint(key, self.slots[index][slot], hash);
+                adapted = true;
+            }
+        }
+        if let Some((key, slot)) = &self.victim {
+            if matches(*slot, hash) && key != x {
+                let next = self.next_fingerprint(key, *slot, hash);
+                self.victim.as_mut().expect("checked above").1 = next;
+                adapted = true;
+            }
+        }
+        adapted
+    }
+
+    /// Re-encodes the slot holding `key` with the next selector whose
+    /// fingerprint differs from that of the non-member with `hash`. Some
+    /// selector always separates the two keys unless all four
+    /// fingerprints collide, which is vanishingly rare.
+    fn next_fingerprint(&self, key: &T, slot: u16, hash: u64) -> u16 {
+        let key_hash = self.fingerprint_hash(key);
+        let mut selector = selector_of(slot);
+        for _ in 1..SELECTORS {
+            selector = (selector + 1) % SELECTORS;
+            if fingerprint(key_hash, selector) != fingerprint(hash, selector) {
+                break;
+            }
+        }
+        encode(key_hash, selector)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fraction of slots holding a key.
+    pub fn load(&self) -> f64 {
+        self.len as f64 / (self.slots.len() * SLOTS) as f64
+    }
+
+    /// Expected false positive rate at the current load, before any
+    /// adaptation. Reporting false positives only lowers it for the
+    /// queries that were reported.
+    pub fn false_positive_rate(&self) -> f64 {
+        false_positive_rate(self.load(), FINGERPRINT_MASK)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn has_victim(&self) -> bool {
+        self.victim.is_some()
+    }
+
+    /// Number of insertions in a row that ran out of room. Reset by the
+    /// next insertion that fits.
+    pub fn consecutive_failures(&self) -> u64 {
+        self.failures.consecutive
+    }
+
+    /// Number of insertions that ran out of room since the filter was
+    /// created.
+    pub fn failed_inserts(&self) -> u64 {
+        self.failures.total
+    }
+
+    /// Returns `true` once the filter rejects new keys or has reached the
+    /// load it was sized for.
+    pub fn is_saturated(&self) -> bool {
+        self.victim.is_some() || self.load() >= TARGET_LOAD
+    }
+
+    fn find(&self, x: &T) -> Option<(usize, usize)> {
+        let (i1, i2) = self.indices(x);
+        [i1, i2].into_iter().find_map(|index| {
+            self.keys[index]
+                .iter()
+                .position(|key| key.as_ref() == Some(x))
+                .map(|slot| (index, slot))
+        })
+    }
+
+    fn place(&mut self, index: usize, entry: (T, u16)) -> Result<(), (T, u16)> {
+        match self.slots[index].iter().position(|&slot| slot == EMPTY) {
+            Some(slot) => {
+                self.slots[index][slot] = entry.1;
+                self.keys[index][slot] = Some(entry.0);
+                Ok(())
+            }
+            None => Err(entry),
+        }
+    }
+
+    fn encode(&self, x: &T, selector: u16) -> u16 {
+        encode(self.fingerprint_hash(x), selector)
+    }
+
+    /// Both candidate buckets come from one 64-bit hash of the key. They
+    /// cannot depend on the fingerprint because that changes as the
+    /// filter adapts.
+    fn indices(&self, x: &T) -> (usize, usize) {
+        let mut hasher = self.index_hasher.clone();
+        x.hash(&mut hasher);
+        let hash = hasher.finish();
+        let mask = self.slots.len() - 1;
+        (hash as u32 as usize & mask, (hash >> 32) as usize & mask)
+    }
+
+    fn fingerprint_hash(&self, x: &T) -> u64 {
+        let mut hasher = self.fingerprint_hasher.clone();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<T: Hash + Eq + Clone> SlotBuckets for AdaptiveCuckooFilter<T> {
+    type Bucket = usize;
+    /// A key and its slot, mirroring `keys` and `slots`. The key is only
+    /// taken out once the entry has been placed.
+    type Entry = (Option<T>, u16);
+    type Error = Infallible;
+
+    fn random_slot(&mut self) -> usize {
+        (xorshift(&mut self.rng) as usize) % SLOTS
+    }
+
+    fn alternate(&self, index: usize, entry: &Self::Entry) -> usize {
+        let (a, b) = self.indices(entry.0.as_ref().expect("a carried entry has its key"));
+        if a == index {
+            b
+        } else {
+            a
+        }
+    }
+
+    fn place(&mut self, index: usize, entry: &mut Self::Entry) -> Result<bool, Infallible> {
+        let key = entry.0.take().expect("a carried entry has its key");
+        match self.place(index, (key, entry.1)) {
+            Ok(()) => Ok(true),
+            Err((key, _)) => {
+                entry.0 = Some(key);
+                Ok(false)
+            }
+        }
+    }
+
+    fn swap(&mut self, index: usize, slot: usize, entry: &mut Self::Entry) -> Result<(), Infallible> {
+        mem::swap(&mut self.keys[index][slot], &mut entry.0);
+        mem::swap(&mut self.slots[index][slot], &mut entry.1);
+        Ok(())
+    }
+}
+
+/// Fingerprint number `selector` of a key: one of four 16-bit windows of
+/// its fingerprint hash, cut down to 14 bits and never zero.
+fn fingerprint(hash: u64, selector: u16) -> u16 {
+    ((hash >> (16 * u32::from(selector))) as u16 & FINGERPRINT_MASK).max(1)
+}
+
+fn encode(hash: u64, selector: u16) -> u16 {
+    selector << SELECTOR_SHIFT | fingerprint(hash, selector)
+}
+
+fn selector_of(slot: u16) -> u16 {
+    slot >> SELECTOR_SHIFT
+}
+
+fn matches(slot: u16, hash: u64) -> bool {
+    slot != EMPTY && slot & FINGERPRINT_MASK == fingerprint(hash, selector_of(slot))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::AdaptiveCuckooFilter;
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut filter = AdaptiveCuckooFilter::new(100);
+        assert!(filter.insert(1));
+        assert!(!filter.insert(1));
+        assert!(filter.contains(&1));
+        assert_eq!(filter.len(), 1);
+        assert!(filter.remove(&1));
+        assert!(!filter.remove(&1));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_reported_false_positives_stop_matching() {
+        let capacity = 5_000;
+        let mut filter = AdaptiveCuckooFilter::with_seed(capacity, 3);
+        for x in 0..capacity as u64 {
+            assert!(filter.insert(x));
+        }
+        let false_positives: Vec<u64> = (1_000_000..1_200_000).filter(|x| filter.contains(x)).collect();
+        assert!(!false_positives.is_empty());
+        for x in &false_positives {
+            filter.report_false_positive(x);
+        }
+        let remaining = false_positives.iter().filter(|x| filter.contains(x)).count();
+        assert!(remaining * 20 < false_positives.len(), "{remaining} of {} still match", false_positives.len());
+        assert!((0..capacity as u64).all(|x| filter.contains(&x)));
+        assert!(!filter.report_false_positive(&0));
+    }
+
+    #[test]
+    fn test_fills_up_without_losing_keys() {
+        let mut filter = AdaptiveCuckooFilter::with_seed(16, 9);
+        let inserted: Vec<u32> = (0..100).take_while(|&x| filter.insert(x)).collect();
+        assert!(inserted.len() >= 16);
+        assert!(inserted.iter().all(|x| filter.contains(x)));
+        assert_eq!(filter.len(), inserted.len());
+        assert!(filter.is_saturated());
+        assert_eq!(filter.consecutive_failures(), 2);
+    }
+
+    #[test]
+    fn test_shrink_to_keeps_keys_and_adaptations() {
+        let mut filter = AdaptiveCuckooFilter::with_seed(50_000, 4);
+        for x in 0..2_000u64 {
+            assert!(filter.insert(x));
+        }
+        let false_positives: Vec<u64> = (1_000_000..1_500_000).filter(|x| filter.contains(x)).collect();
+        for x in &false_positives {
+            filter.report_false_positive(x);
+        }
+        let fixed: Vec<u64> = false_positives.into_iter().filter(|x| !filter.contains(x)).collect();
+        assert!(!fixed.is_empty());
+
+        let load = filter.load();
+        assert!(!filter.shrink_to(100_000));
+        assert!(filter.shrink_to(2_000));
+        assert!(filter.load() > 4.0 * load);
+        assert_eq!(filter.len(), 2_000);
+        assert!((0..2_000u64).all(|x| filter.contains(&x)));
+        assert!(fixed.iter().all(|x| !filter.contains(x)));
+        assert!(filter.insert(2_000));
+    }
+}
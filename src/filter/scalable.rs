@@ -0,0 +1,239 @@
+//! Filters that keep their false positive rate however many keys they
+//! are given, and the stream deduplicator built on them.
+
+use std::hash::Hash;
+use std::mem;
+
+use super::{false_positive_rate, CuckooMapFilter, FINGERPRINT_MASK, TARGET_LOAD};
+
+/// Each layer gets this fraction of the previous layer's share of the
+/// false positive budget.
+const TIGHTENING: f64 = 0.9;
+
+/// A filter that keeps its false positive rate under a budget however
+/// many keys it is given, in the manner of a scalable Bloom filter.
+///
+/// Keys go into the newest layer until its own rate reaches its share of
+/// the budget or it saturates. Then a layer twice as large is opened.
+/// Layer `i` gets `fpr * 0.1 * 0.9^i` of the budget, so the shares of all
+/// layers add up to at most `fpr`.
+///
+/// Like the layers of a scalable Bloom filter, each layer is given
+/// fingerprints long enough to meet its share at the load it is sized
+/// for, in steps of 12 bits: a layer stacks as many [`CuckooMapFilter`]s
+/// with independent hash functions as it needs, and holds a key only if
+/// all of them do. Memory grows with the number of distinct keys but not
+/// with their size.
+#[derive(Clone)]
+pub struct ScalableCuckooFilter<T: ?Sized> {
+    layers: Vec<Layer<T>>,
+    /// Capacity the newest layer was created with.
+    capacity: usize,
+    fpr: f64,
+}
+
+impl<T: Hash + ?Sized> ScalableCuckooFilter<T> {
+    /// Creates a filter whose first layer has room for about `capacity`
+    /// keys and whose false positive rate stays below `fpr`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fpr` is not strictly between 0 and 1.
+    pub fn new(capacity: usize, fpr: f64) -> Self {
+        assert!(fpr > 0.0 && fpr < 1.0, "false positive rate {fpr} is not between 0 and 1");
+        let capacity = capacity.max(1);
+        ScalableCuckooFilter { layers: vec![Layer::new(capacity, Self::share(fpr, 0))], capacity, fpr }
+    }
+
+    /// Returns `true` if some layer holds a fingerprint matching `x`.
+    pub fn contains(&self, x: &T) -> bool {
+        self.layers.iter().any(|layer| layer.contains(x))
+    }
+
+    /// Records `x` and returns `true` if it was not found before. With
+    /// probability below `fpr` a key that was never recorded is found
+    /// anyway, and is then not recorded.
+    pub fn insert(&mut self, x: &T) -> bool {
+        if self.contains(x) {
+            return false;
+        }
+        let top = self.layers.last().expect("there is always a layer");
+        if top.is_saturated() || top.false_positive_rate() >= top.share {
+            self.grow();
+        }
+        if !self.layers.last_mut().expect("there is always a layer").insert(x) {
+            // The insertion that saturates a layer can still fail. A
+            // fresh, larger layer always has room.
+            self.grow();
+            self.layers.last_mut().expect("there is always a layer").insert(x);
+        }
+        true
+    }
+
+    /// Returns the number of layers opened so far.
+    pub fn layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn grow(&mut self) {
+        self.capacity *= 2;
+        let share = Self::share(self.fpr, self.layers.len());
+        self.layers.push(Layer::new(self.capacity, share));
+    }
+
+    /// Share of the false positive budget `fpr` given to layer `index`.
+    fn share(fpr: f64, index: usize) -> f64 {
+        fpr * (1.0 - TIGHTENING) * TIGHTENING.powi(index as i32)
+    }
+}
+
+/// One layer of a [`ScalableCuckooFilter`]: filters with independent hash
+/// functions that each hold every key of the layer, so that together
+/// their fingerprints are as long as the layer's share requires.
+#[derive(Clone)]
+struct Layer<T: ?Sized> {
+    filters: Vec<CuckooMapFilter<T>>,
+    share: f64,
+}
+
+impl<T: Hash + ?Sized> Layer<T> {
+    /// Creates a layer with room for about `capacity` keys, with enough
+    /// filters that its false positive rate stays below `share` until they
+    /// reach the load they are sized for.
+    fn new(capacity: usize, share: f64) -> Self {
+        let full_rate = false_positive_rate(TARGET_LOAD, FINGERPRINT_MASK);
+        let count = (share.ln() / full_rate.ln()).ceil().max(1.0) as usize;
+        Layer { filters: (0..count).map(|_| CuckooMapFilter::new(capacity)).collect(), share }
+    }
+
+    fn contains(&self, x: &T) -> bool {
+        self.filters.iter().all(|filter| filter.contains(x))
+    }
+
+    /// Adds `x` to every filter. If one of them rejects it, the filters
+    /// before it keep an entry the layer never reports, which only costs
+    /// space.
+    fn insert(&mut self, x: &T) -> bool {
+        self.filters.iter_mut().all(|filter| filter.insert(x, 0))
+    }
+
+    fn is_saturated(&self) -> bool {
+        self.filters.iter().any(CuckooMapFilter::is_saturated)
+    }
+
+    /// The filters' hash functions are independent, so a key that was
+    /// never inserted matches all of them with the product of their rates.
+    fn false_positive_rate(&self) -> f64 {
+        self.filters.iter().map(CuckooMapFilter::false_positive_rate).product()
+    }
+}
+
+/// Tells which items of a stream are new, remembering them in
+/// [`ScalableCuckooFilter`]s rather than keeping the items themselves.
+///
+/// A repeated item is never reported as new. A first occurrence is
+/// reported as a repeat with probability below the `fpr` the
+/// deduplicator was created with.
+///
+/// Items are remembered for good unless `advance` is called. To bound
+/// memory on an endless stream, call it periodically: like
+/// [`WindowedCuckooSet`](crate::window::WindowedCuckooSet), the
+/// deduplicator keeps a current and a previous generation, and an item
+/// is forgotten once two generations have passed without it.
+#[derive(Clone)]
+pub struct Deduplicator<T: ?Sized> {
+    current: ScalableCuckooFilter<T>,
+    previous: ScalableCuckooFilter<T>,
+    capacity: usize,
+    /// Budget of each generation, half of the overall one because a
+    /// lookup consults both.
+    fpr: f64,
+}
+
+impl<T: Hash + ?Sized> Deduplicator<T> {
+    /// Creates a deduplicator for about `capacity` distinct items per
+    /// generation, which reports first occurrences as repeats with
+    /// probability below `fpr`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fpr` is not strictly between 0 and 1.
+    pub fn new(capacity: usize, fpr: f64) -> Self {
+        assert!(fpr > 0.0 && fpr < 1.0, "false positive rate {fpr} is not between 0 and 1");
+        let fpr = fpr / 2.0;
+        Deduplicator {
+            current: ScalableCuckooFilter::new(capacity, fpr),
+            previous: ScalableCuckooFilter::new(1, fpr),
+            capacity,
+            fpr,
+        }
+    }
+
+    /// Returns `true` if `x` has not been seen within the window, and
+    /// remembers it either way.
+    pub fn is_new(&mut self, x: &T) -> bool {
+        if self.previous.contains(x) {
+            // Refresh it so that it survives the next `advance`.
+            self.current.insert(x);
+            return false;
+        }
+        self.current.insert(x)
+    }
+
+    /// Starts a new generation, forgetting the items that were last seen
+    /// before the previous one started.
+    pub fn advance(&mut self) {
+        let fresh = ScalableCuckooFilter::new(self.capacity, self.fpr);
+        self.previous = mem::replace(&mut self.current, fresh);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Deduplicator, ScalableCuckooFilter};
+
+    #[test]
+    fn test_scalable_filter_grows_within_budget() {
+        let mut filter = ScalableCuckooFilter::new(64, 0.01);
+        let new = (0..20_000u32).filter(|x| filter.insert(x)).count();
+        assert!(filter.layers() > 1);
+        assert!(new > 19_700, "only {new} keys were new");
+        assert!((0..20_000u32).all(|x| filter.contains(&x)));
+        assert!(!filter.insert(&7));
+        let false_positives = (20_000..120_000u32).filter(|x| filter.contains(x)).count();
+        assert!(false_positives < 1_200, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_layers_lengthen_fingerprints_for_small_rates() {
+        let mut filter = ScalableCuckooFilter::new(64, 1e-5);
+        assert!(filter.layers[0].filters.len() > 1);
+        let new = (0..20_000u32).filter(|x| filter.insert(x)).count();
+        assert_eq!(new, 20_000);
+        // Every layer but the newest filled up to the load it was sized
+        // for instead of opening the next one while still nearly empty.
+        assert!(filter.layers() < 10, "{} layers", filter.layers());
+        let false_positives = (20_000..220_000u32).filter(|x| filter.contains(x)).count();
+        assert!(false_positives < 10, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_deduplicator_window() {
+        let mut dedup = Deduplicator::new(1_000, 0.001);
+        assert!(dedup.is_new("a"));
+        assert!(dedup.is_new("b"));
+        assert!(!dedup.is_new("a"));
+        dedup.advance();
+        assert!(!dedup.is_new("a"));
+        dedup.advance();
+        // "a" was refreshed in the previous generation; "b" was not.
+        assert!(!dedup.is_new("a"));
+        assert!(dedup.is_new("b"));
+    }
+
+    #[test]
+    #[should_panic(expected = "not between 0 and 1")]
+    fn test_rejects_invalid_rate() {
+        let _ = Deduplicator::<str>::new(10, 1.0);
+    }
+}
@@ -0,0 +1,51 @@
+//! Transparent huge page advice for large, flat allocations.
+//!
+//! Random probes into storage of many gigabytes miss the TLB on almost
+//! every access with 4 KiB pages. Backing the storage with 2 MiB pages
+//! cuts those misses, but the kernel only does so for memory that was
+//! advised before it was first touched. Only whole, aligned 2 MiB pages
+//! can be huge, and the allocator decides where storage starts, so small
+//! allocations and the unaligned ends of large ones keep normal pages.
+
+use std::mem::{self, MaybeUninit};
+
+const HUGE_PAGE: usize = 2 << 20;
+
+/// Asks the kernel to back the whole huge pages within `spare` with
+/// transparent huge pages. Call this on a fresh allocation before
+/// writing to it. Failures are ignored.
+pub(crate) fn advise<T>(spare: &mut [MaybeUninit<T>]) {
+    let start = spare.as_mut_ptr() as usize;
+    let end = start + mem::size_of_val(spare);
+    let (start, end) = (start.next_multiple_of(HUGE_PAGE), end / HUGE_PAGE * HUGE_PAGE);
+    if start < end {
+        // SAFETY: the range lies within the allocation backing `spare`,
+        // and MADV_HUGEPAGE does not change its contents.
+        unsafe {
+            libc::madvise(start as *mut libc::c_void, end - start, libc::MADV_HUGEPAGE);
+        }
+    }
+}
+
+/// Returns `true` if the mapping holding `x` was advised to use huge
+/// pages, according to its `hg` flag in `/proc/self/smaps`.
+#[cfg(test)]
+pub(crate) fn is_advised<T>(x: &T) -> bool {
+    let address = x as *const T as usize;
+    let smaps = std::fs::read_to_string("/proc/self/smaps").expect("smaps is readable");
+    let mut inside = false;
+    for line in smaps.lines() {
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if inside {
+                return flags.split_whitespace().any(|flag| flag == "hg");
+            }
+        } else if let Some((range, _)) = line.split_once(' ') {
+            if let Some((start, end)) = range.split_once('-') {
+                if let (Ok(start), Ok(end)) = (usize::from_str_radix(start, 16), usize::from_str_radix(end, 16)) {
+                    inside = (start..end).contains(&address);
+                }
+            }
+        }
+    }
+    false
+}
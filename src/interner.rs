@@ -0,0 +1,158 @@
+//! Interning: mapping values to small, stable integer symbols.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::cuckoo::{random_hasher, CuckooHashTable};
+
+/// An entry of the lookup table. It hashes as the hash of the value it
+/// stands for, so a value can be found without being stored twice.
+#[derive(Clone, PartialEq, Eq)]
+struct Entry {
+    hash: u64,
+    symbol: u32,
+}
+
+impl Hash for Entry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+/// Hands out a `u32` symbol for every distinct value and resolves the
+/// symbol back to the value.
+///
+/// Symbols are assigned in insertion order starting at zero and never
+/// change. Values are stored once, in an arena indexed by symbol; the
+/// cuckoo table only holds each value's hash and symbol and compares
+/// candidates against the arena.
+#[derive(Clone)]
+pub struct CuckooInterner<T> {
+    values: Vec<T>,
+    table: CuckooHashTable<Entry>,
+    hasher: DefaultHasher,
+}
+
+impl<T: Hash + Eq> Default for CuckooInterner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq> CuckooInterner<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty interner with room for `capacity` values before
+    /// either its arena or its table has to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut table = CuckooHashTable::new();
+        table.reserve(capacity);
+        CuckooInterner { values: Vec::with_capacity(capacity), table, hasher: random_hasher() }
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, x: &Q) -> u64 {
+        let mut hasher = self.hasher.clone();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn find<Q: Hash + Eq + ?Sized>(&self, hash: u64, x: &Q) -> Option<u32>
+    where
+        T: Borrow<Q>,
+    {
+        self.table
+            .find_with(&hash, |entry| entry.hash == hash && self.values[entry.symbol as usize].borrow() == x)
+            .map(|entry| entry.symbol)
+    }
+
+    fn push(&mut self, hash: u64, value: T) -> u32 {
+        let symbol = u32::try_from(self.values.len()).expect("interner holds more than u32::MAX values");
+        self.values.push(value);
+        self.table.insert(Entry { hash, symbol });
+        symbol
+    }
+
+    /// Returns the symbol of `value`, assigning the next one if the value
+    /// is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interner already holds `u32::MAX + 1` values.
+    pub fn intern(&mut self, value: T) -> u32 {
+        let hash = self.hash(&value);
+        match self.find(hash, &value) {
+            Some(symbol) => symbol,
+            None => self.push(hash, value),
+        }
+    }
+
+    /// Like `intern`, but takes a borrowed form of the value and only
+    /// makes an owned copy if the value is new.
+    pub fn intern_owned<Q>(&mut self, value: &Q) -> u32
+    where
+        Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+        T: Borrow<Q>,
+    {
+        let hash = self.hash(value);
+        match self.find(hash, value) {
+            Some(symbol) => symbol,
+            None => self.push(hash, value.to_owned()),
+        }
+    }
+
+    /// Returns the symbol of `value` if it has been interned.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, value: &Q) -> Option<u32>
+    where
+        T: Borrow<Q>,
+    {
+        self.find(self.hash(value), value)
+    }
+
+    /// Returns the value `symbol` stands for.
+    pub fn resolve(&self, symbol: u32) -> Option<&T> {
+        self.values.get(symbol as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterates over every symbol and its value, in symbol order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (u32, &T)> {
+        self.values.iter().enumerate().map(|(symbol, value)| (symbol as u32, value))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CuckooInterner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.values.iter().enumerate()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interner::CuckooInterner;
+
+    #[test]
+    fn test_symbols_are_stable() {
+        let mut interner = CuckooInterner::new();
+        let words: Vec<String> = (0..5000).map(|i| format!("ident_{}", i % 2000)).collect();
+        let symbols: Vec<u32> = words.iter().map(|word| interner.intern_owned(word.as_str())).collect();
+        assert_eq!(interner.len(), 2000);
+        for (word, &symbol) in words.iter().zip(&symbols) {
+            assert_eq!(interner.resolve(symbol), Some(word));
+            assert_eq!(interner.get(word.as_str()), Some(symbol));
+            assert_eq!(interner.intern(word.clone()), symbol);
+        }
+        assert!(interner.iter().all(|(symbol, word)| *word == words[symbol as usize]));
+        assert_eq!(interner.get("missing"), None);
+        assert_eq!(interner.resolve(2000), None);
+    }
+}
@@ -0,0 +1,67 @@
+//! The kick chain shared by the structures whose buckets hold several
+//! entries: the filters and the on-disk index.
+//!
+//! Each round swaps the carried entry with a random one in its full
+//! bucket and carries the evicted entry to its other bucket, until one
+//! finds a free slot or the chain runs out.
+
+/// Buckets of several slots that a kick chain can move entries between.
+pub(crate) trait SlotBuckets {
+    type Bucket: Copy;
+    type Entry;
+    /// What a failed read or write of a bucket reports. In-memory buckets
+    /// use `Infallible`.
+    type Error;
+
+    /// A random slot index, below the number of slots per bucket.
+    fn random_slot(&mut self) -> usize;
+
+    /// The bucket other than `bucket` that `entry` may live in.
+    fn alternate(&self, bucket: Self::Bucket, entry: &Self::Entry) -> Self::Bucket;
+
+    /// Stores `entry` in a free slot of `bucket` and returns `true`, or
+    /// returns `false` if the bucket is full. After a success `entry` is
+    /// left over and must not be stored again.
+    fn place(&mut self, bucket: Self::Bucket, entry: &mut Self::Entry) -> Result<bool, Self::Error>;
+
+    /// Swaps `entry` with the one in `slot` of `bucket`. On error `entry`
+    /// still holds what it held before.
+    fn swap(&mut self, bucket: Self::Bucket, slot: usize, entry: &mut Self::Entry) -> Result<(), Self::Error>;
+}
+
+/// Carries `entry`, which belongs in the full bucket `bucket`, along a
+/// chain of at most `max_kicks` swaps. Returns `None` once it is placed,
+/// or the bucket of the entry still carried in `entry` if the chain ran
+/// out. `on_kick` sees every swap, so the caller can undo the chain with
+/// [`undo_kicks`].
+pub(crate) fn kick<B: SlotBuckets>(
+    buckets: &mut B,
+    mut bucket: B::Bucket,
+    entry: &mut B::Entry,
+    max_kicks: usize,
+    mut on_kick: impl FnMut(B::Bucket, usize),
+) -> Result<Option<B::Bucket>, B::Error> {
+    for _ in 0..max_kicks {
+        let slot = buckets.random_slot();
+        buckets.swap(bucket, slot, entry)?;
+        on_kick(bucket, slot);
+        bucket = buckets.alternate(bucket, entry);
+        if buckets.place(bucket, entry)? {
+            return Ok(None);
+        }
+    }
+    Ok(Some(bucket))
+}
+
+/// Reverses the swaps `path` recorded, newest first, so that `entry`
+/// holds the entry the chain started with again.
+pub(crate) fn undo_kicks<B: SlotBuckets>(
+    buckets: &mut B,
+    entry: &mut B::Entry,
+    path: &[(B::Bucket, usize)],
+) -> Result<(), B::Error> {
+    for &(bucket, slot) in path.iter().rev() {
+        buckets.swap(bucket, slot, entry)?;
+    }
+    Ok(())
+}
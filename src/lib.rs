@@ -1 +1,16 @@
-pub mod cuckoo;
\ No newline at end of file
+pub mod cuckoo;
+pub mod disk;
+pub mod error;
+pub mod ffi;
+pub mod filter;
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+mod hugepages;
+pub mod interner;
+mod kick;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod window;
@@ -0,0 +1,163 @@
+//! Metrics export through the `metrics` facade, enabled with the
+//! `metrics` feature.
+//!
+//! Call one of the `record_*` functions periodically, for example from
+//! the task that scrapes or flushes the exporter. Every metric carries a
+//! `name` label so several tables or filters can share one registry, and
+//! whichever exporter the process installed (Prometheus or otherwise)
+//! picks the values up.
+
+use std::hash::Hash;
+
+use metrics::{counter, gauge};
+
+use crate::cuckoo::CuckooHashTable;
+use crate::filter::{AdaptiveCuckooFilter, CuckooMapFilter};
+
+/// Records the size, capacity and work counters of `table`.
+pub fn record_table<T: Hash + Clone + Eq>(name: &str, table: &CuckooHashTable<T>) {
+    let label = [("name", name.to_owned())];
+    let stats = table.stats();
+    gauge!("cuckoo_table_len", &label).set(table.len() as f64);
+    gauge!("cuckoo_table_capacity", &label).set(table.capacity() as f64);
+    counter!("cuckoo_table_resizes_total", &label).absolute(stats.resizes);
+    counter!("cuckoo_table_displacements_total", &label).absolute(stats.displacements);
+    counter!("cuckoo_table_evictions_total", &label).absolute(stats.evictions);
+}
+
+/// Records the occupancy, failures and expected false positive rate of
+/// `filter`.
+pub fn record_filter<T: Hash + ?Sized>(name: &str, filter: &CuckooMapFilter<T>) {
+    record_filter_health(
+        name,
+        FilterHealth {
+            len: filter.len(),
+            load: filter.load(),
+            victims: usize::from(filter.has_victim()),
+            failed_inserts: filter.failed_inserts(),
+            false_positive_rate: filter.false_positive_rate(),
+        },
+    );
+}
+
+/// Like `record_filter`, for an adaptive filter.
+pub fn record_adaptive_filter<T: Hash + Eq + Clone>(name: &str, filter: &AdaptiveCuckooFilter<T>) {
+    record_filter_health(
+        name,
+        FilterHealth {
+            len: filter.len(),
+            load: filter.load(),
+            victims: usize::from(filter.has_victim()),
+            failed_inserts: filter.failed_inserts(),
+            false_positive_rate: filter.false_positive_rate(),
+        },
+    );
+}
+
+struct FilterHealth {
+    len: usize,
+    load: f64,
+    victims: usize,
+    failed_inserts: u64,
+    false_positive_rate: f64,
+}
+
+fn record_filter_health(name: &str, health: FilterHealth) {
+    let label = [("name", name.to_owned())];
+    gauge!("cuckoo_filter_len", &label).set(health.len as f64);
+    gauge!("cuckoo_filter_load", &label).set(health.load);
+    gauge!("cuckoo_filter_victims", &label).set(health.victims as f64);
+    gauge!("cuckoo_filter_false_positive_rate", &label).set(health.false_positive_rate);
+    counter!("cuckoo_filter_failed_inserts_total", &label).absolute(health.failed_inserts);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{Counter, CounterFn, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+    use crate::cuckoo::CuckooHashTable;
+    use crate::filter::CuckooMapFilter;
+    use crate::metrics::{record_filter, record_table};
+
+    /// Keeps the last value written to every counter and gauge, keyed by
+    /// metric name.
+    #[derive(Default)]
+    struct LastValues(Mutex<HashMap<String, Arc<Value>>>);
+
+    #[derive(Default)]
+    struct Value(AtomicU64);
+
+    impl CounterFn for Value {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::Relaxed);
+        }
+    }
+
+    impl GaugeFn for Value {
+        fn increment(&self, _: f64) {}
+
+        fn decrement(&self, _: f64) {}
+
+        fn set(&self, value: f64) {
+            self.0.store(value as u64, Ordering::Relaxed);
+        }
+    }
+
+    impl LastValues {
+        fn value(&self, key: &Key) -> Arc<Value> {
+            Arc::clone(self.0.lock().unwrap().entry(key.name().to_owned()).or_default())
+        }
+
+        fn get(&self, name: &str) -> u64 {
+            self.0.lock().unwrap()[name].0.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Recorder for LastValues {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.value(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.value(key))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let recorder = LastValues::default();
+        let mut table = CuckooHashTable::with_capacity(4);
+        let mut filter = CuckooMapFilter::new(1000);
+        for x in 0..100 {
+            table.insert(x);
+            filter.insert(&x, 1);
+        }
+        metrics::with_local_recorder(&recorder, || {
+            record_table("users", &table);
+            record_filter("users", &filter);
+        });
+        assert_eq!(recorder.get("cuckoo_table_len"), 100);
+        assert_eq!(recorder.get("cuckoo_table_capacity"), table.capacity() as u64);
+        assert_eq!(recorder.get("cuckoo_table_resizes_total"), table.stats().resizes);
+        assert_eq!(recorder.get("cuckoo_filter_len"), filter.len() as u64);
+        assert_eq!(recorder.get("cuckoo_filter_victims"), 0);
+    }
+}
@@ -0,0 +1,47 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! Build the crate as an extension module with the `extension-module`
+//! feature (for example `maturin build --features extension-module`) and
+//! import it as `cuckoo_rs`. The `python` feature alone links against
+//! libpython, so `cargo test --features python` works. Keys are Python
+//! integers that fit in a signed 64-bit value.
+
+use pyo3::prelude::*;
+
+use crate::cuckoo::CuckooHashTable;
+
+/// Python wrapper around a `CuckooHashTable<i64>`.
+#[pyclass(name = "CuckooTable")]
+pub struct PyCuckooTable {
+    inner: CuckooHashTable<i64>,
+}
+
+#[pymethods]
+impl PyCuckooTable {
+    #[new]
+    fn new() -> Self {
+        PyCuckooTable { inner: CuckooHashTable::new() }
+    }
+
+    fn insert(&mut self, key: i64) -> bool {
+        self.inner.insert(key)
+    }
+
+    fn contains(&self, key: i64) -> bool {
+        self.inner.contains(&key)
+    }
+
+    fn delete(&mut self, key: i64) -> bool {
+        self.inner.remove(&key)
+    }
+
+    fn __contains__(&self, key: i64) -> bool {
+        self.inner.contains(&key)
+    }
+}
+
+#[pymodule]
+fn cuckoo_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCuckooTable>()?;
+    Ok(())
+}
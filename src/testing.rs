@@ -0,0 +1,81 @@
+//! Test support for crates that consume this one, enabled with the
+//! `testing` feature.
+//!
+//! `CuckooHashTable<T>` implements `quickcheck::Arbitrary`, producing
+//! tables of varied capacities filled to varied load factors. The hash
+//! seeds are drawn from the generator too, so a generator with a fixed
+//! seed always produces the same tables.
+
+use std::hash::Hash;
+
+use quickcheck::{Arbitrary, Gen};
+
+use crate::cuckoo::CuckooHashTable;
+
+/// Generated tables have between 16 and 256 buckets per bucket group.
+const MAX_CAPACITY_SHIFT: u8 = 4;
+
+impl<T: Arbitrary + Hash + Eq> Arbitrary for CuckooHashTable<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let capacity = 16 << (u8::arbitrary(g) % (MAX_CAPACITY_SHIFT + 1));
+        // Loads above one half of all slots almost always force a resize,
+        // so the target is drawn from [0, 0.5].
+        let load = f64::from(u8::arbitrary(g) % 51) / 100.0;
+        let target = (2.0 * capacity as f64 * load) as usize;
+        let mut table = CuckooHashTable::with_capacity_and_seeds(capacity, u64::arbitrary(g), u64::arbitrary(g));
+        // Element types with few inhabitants cannot reach every target,
+        // so the number of attempts is bounded.
+        for _ in 0..target * 2 {
+            if table.len() >= target {
+                break;
+            }
+            table.insert(T::arbitrary(g));
+        }
+        table
+    }
+
+    /// Shrinks the elements but keeps the hash functions and resize
+    /// policy, and starts each candidate at the same capacity, so
+    /// candidates stay close to the failing table. A candidate still
+    /// grows if its elements cannot be placed at that capacity.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let items: Vec<T> = self.iter().cloned().collect();
+        let empty = self.empty_like(self.capacity());
+        Box::new(items.shrink().map(move |items| {
+            let mut table = empty.clone();
+            for x in items {
+                table.insert(x);
+            }
+            table
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::{Arbitrary, Gen};
+    use quickcheck_macros::quickcheck;
+
+    use crate::cuckoo::CuckooHashTable;
+
+    #[quickcheck]
+    fn prop_arbitrary_tables_are_consistent(table: CuckooHashTable<u32>) -> bool {
+        table.check_invariants().is_ok() && table.iter().all(|x| table.contains(x))
+    }
+
+    #[test]
+    fn test_shrink_produces_smaller_tables() {
+        for seed in 0..20 {
+            let mut g = Gen::from_size_and_seed(100, seed);
+            let table = loop {
+                let table = CuckooHashTable::<u32>::arbitrary(&mut g);
+                if !table.is_empty() {
+                    break table;
+                }
+            };
+            assert!(table
+                .shrink()
+                .all(|smaller| smaller.len() <= table.len() && smaller.capacity() >= table.capacity()));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Membership over a rolling window, for "seen in the last N minutes"
+//! checks such as replay protection and rate limiting.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::Chain;
+use std::mem;
+
+use crate::cuckoo::{self, CuckooHashTable};
+
+/// A set that forgets elements two generations after they were last
+/// inserted.
+///
+/// Elements are inserted into the current generation. `advance` makes
+/// the current generation the previous one and starts an empty current
+/// generation, dropping whatever was only in the old previous one. An
+/// element that is inserted again while it is in the previous generation
+/// moves back to the current one, so the two generations never overlap.
+///
+/// Calling `advance` every `N / 2` minutes remembers every element for at
+/// least `N / 2` and at most `N` minutes after its last insertion.
+#[derive(Clone)]
+pub struct WindowedCuckooSet<T> {
+    current: CuckooHashTable<T>,
+    previous: CuckooHashTable<T>,
+}
+
+impl<T: Hash + Clone + Eq> Default for WindowedCuckooSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Clone + Eq> WindowedCuckooSet<T> {
+    pub fn new() -> Self {
+        WindowedCuckooSet { current: CuckooHashTable::new(), previous: CuckooHashTable::new() }
+    }
+
+    /// Creates an empty set whose generations each start with `capacity`
+    /// buckets per bucket group.
+    pub fn with_capacity(capacity: usize) -> Self {
+        WindowedCuckooSet {
+            current: CuckooHashTable::with_capacity(capacity),
+            previous: CuckooHashTable::with_capacity(capacity),
+        }
+    }
+
+    /// Inserts `x` into the current generation and returns `true` if it
+    /// was in neither generation.
+    pub fn insert(&mut self, x: T) -> bool {
+        if self.previous.remove(&x) {
+            self.current.insert(x);
+            return false;
+        }
+        self.current.insert(x)
+    }
+
+    /// Returns `true` if `x` is in either generation.
+    pub fn contains<Q: Hash + Eq + ?Sized>(&self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.current.contains(x) || self.previous.contains(x)
+    }
+
+    /// Removes `x` from whichever generation holds it and returns whether
+    /// it was present.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, x: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.current.remove(x) || self.previous.remove(x)
+    }
+
+    /// Starts a new generation and returns how many elements expired.
+    /// The storage of the expired generation is reused for the new one.
+    pub fn advance(&mut self) -> usize {
+        let expired = self.previous.len();
+        mem::swap(&mut self.current, &mut self.previous);
+        self.current.clear();
+        expired
+    }
+
+    /// Returns the number of elements in both generations.
+    pub fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty() && self.previous.is_empty()
+    }
+
+    /// The elements inserted since the last `advance`.
+    pub fn current(&self) -> &CuckooHashTable<T> {
+        &self.current
+    }
+
+    /// The elements that expire on the next `advance`.
+    pub fn previous(&self) -> &CuckooHashTable<T> {
+        &self.previous
+    }
+
+    /// Iterates over the current generation, then the previous one.
+    pub fn iter(&self) -> Chain<cuckoo::Iter<'_, T>, cuckoo::Iter<'_, T>> {
+        self.current.iter().chain(self.previous.iter())
+    }
+}
+
+impl<T: fmt::Debug + Clone> fmt::Debug for WindowedCuckooSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowedCuckooSet")
+            .field("current", &self.current)
+            .field("previous", &self.previous)
+            .finish()
+    }
+}
+
+impl<'a, T: Hash + Clone + Eq> IntoIterator for &'a WindowedCuckooSet<T> {
+    type Item = &'a T;
+    type IntoIter = Chain<cuckoo::Iter<'a, T>, cuckoo::Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::window::WindowedCuckooSet;
+
+    #[test]
+    fn test_elements_expire_after_two_generations() {
+        let mut seen = WindowedCuckooSet::new();
+        assert!(seen.insert("nonce-1"));
+        assert!(!seen.insert("nonce-1"));
+        assert_eq!(seen.advance(), 0);
+        assert!(seen.insert("nonce-2"));
+        assert!(seen.contains("nonce-1"));
+        assert_eq!(seen.advance(), 1);
+        assert!(!seen.contains("nonce-1"));
+        assert!(seen.contains("nonce-2"));
+        assert!(seen.insert("nonce-1"));
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_reinsertion_refreshes() {
+        let mut seen = WindowedCuckooSet::with_capacity(4);
+        for x in 0..100 {
+            seen.insert(x);
+        }
+        seen.advance();
+        for x in 0..50 {
+            assert!(!seen.insert(x));
+        }
+        assert_eq!((seen.current().len(), seen.previous().len()), (50, 50));
+        assert_eq!(seen.iter().count(), 100);
+        assert_eq!(seen.advance(), 50);
+        assert!((0..100).all(|x| seen.contains(&x) == (x < 50)));
+        assert!(seen.remove(&0) && !seen.remove(&0));
+        seen.advance();
+        seen.advance();
+        assert!(seen.is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Soak test that runs randomized workloads against `CuckooHashTable`
+//! and checks its invariants as it goes.
+//!
+//! Run with `cargo test --release --features stress --test stress`.
+//! `CUCKOO_STRESS_SECS` sets how long to run (60 seconds by default) and
+//! `CUCKOO_STRESS_SEED` the seed. The seed is printed up front and in
+//! every failure, and the table's hash functions are derived from it, so
+//! a failing run can be replayed exactly.
+
+use std::collections::HashSet;
+use std::env;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use cuckoo_rs::cuckoo::CuckooHashTable;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Operations between two full checks. Each round also picks a new key
+/// space and insert bias, so the table keeps growing and draining.
+const ROUND_OPS: u64 = 10_000;
+
+struct Run {
+    seed: u64,
+    rng: StdRng,
+    table: CuckooHashTable<u32>,
+    model: HashSet<u32>,
+    ops: u64,
+}
+
+impl Run {
+    fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let table = CuckooHashTable::with_seeds(rng.gen(), rng.gen());
+        Run { seed, rng, table, model: HashSet::new(), ops: 0 }
+    }
+
+    #[track_caller]
+    fn check(&self, ok: bool, what: &str) {
+        assert!(ok, "seed {}, op {}: {what}", self.seed, self.ops);
+    }
+
+    /// Checks the table's own invariants and that it holds exactly the
+    /// model's elements.
+    #[track_caller]
+    fn check_all(&self) {
+        if let Err(violation) = self.table.check_invariants() {
+            self.check(false, &violation);
+        }
+        self.check(self.table.len() == self.model.len(), "len differs from the model");
+        self.check(self.model.iter().all(|x| self.table.contains(x)), "an element was lost");
+    }
+
+    /// Records the outcome of an insertion that may have evicted an
+    /// element instead of growing the table.
+    fn evicted(&mut self, x: u32, evicted: Option<u32>) {
+        self.model.insert(x);
+        if let Some(evicted) = evicted {
+            self.model.remove(&evicted);
+            self.check(!self.table.contains(&evicted), "evicted element is still present");
+        }
+    }
+
+    fn round(&mut self) {
+        let key_space = 1u32 << self.rng.gen_range(4..20);
+        let insert_bias = self.rng.gen_range(0.2..0.8);
+        for _ in 0..ROUND_OPS {
+            self.ops += 1;
+            let x = self.rng.gen_range(0..key_space);
+            match self.rng.gen_range(0..1000) {
+                0 => {
+                    self.table.rehash_with_seeds(self.rng.gen(), self.rng.gen());
+                    self.check_all();
+                }
+                1 => {
+                    self.table.reserve(self.rng.gen_range(0..key_space as usize));
+                    self.check_all();
+                }
+                2 => {
+                    self.table.shrink_to_fit();
+                    self.check_all();
+                }
+                3 => {
+                    let modulus = self.rng.gen_range(2..8);
+                    self.table.retain(|x| x % modulus != 0);
+                    self.model.retain(|x| x % modulus != 0);
+                    self.check_all();
+                }
+                4 if self.rng.gen_bool(0.05) => {
+                    self.table.clear();
+                    self.model.clear();
+                    self.check_all();
+                }
+                5..=14 => {
+                    let evicted = self.table.insert_or_evict(x);
+                    self.evicted(x, evicted);
+                }
+                15..=24 => {
+                    let max_kicks = self.rng.gen_range(0..16);
+                    let evicted = self.table.insert_bounded(x, max_kicks);
+                    self.evicted(x, evicted);
+                }
+                25..=99 => {
+                    let agrees = self.table.contains_or_insert(x) != self.model.insert(x);
+                    self.check(agrees, "contains_or_insert disagrees with the model");
+                }
+                _ if self.rng.gen_bool(insert_bias) => {
+                    let agrees = self.table.insert(x) == self.model.insert(x);
+                    self.check(agrees, "insert disagrees with the model");
+                }
+                _ => {
+                    let agrees = self.table.remove(&x) == self.model.remove(&x);
+                    self.check(agrees, "remove disagrees with the model");
+                }
+            }
+        }
+        self.check_all();
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    let value = env::var(name).ok()?;
+    Some(value.parse().unwrap_or_else(|_| panic!("{name} must be a number, got {value:?}")))
+}
+
+fn main() {
+    let seed = env_u64("CUCKOO_STRESS_SEED")
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).expect("clock is after 1970").as_nanos() as u64);
+    let duration = Duration::from_secs(env_u64("CUCKOO_STRESS_SECS").unwrap_or(60));
+    println!("stress: seed {seed}, running for {duration:?}");
+    let start = Instant::now();
+    let mut run = Run::new(seed);
+    while start.elapsed() < duration {
+        run.round();
+    }
+    let stats = run.table.stats();
+    println!(
+        "stress: {} ops, {} resizes, {} displacements, {} evictions, final len {}",
+        run.ops,
+        stats.resizes,
+        stats.displacements,
+        stats.evictions,
+        run.table.len()
+    );
+}